@@ -10,6 +10,7 @@ fn main() {
 
     bindgen("UniswapV2Router02");
     bindgen("UniswapV3SmartRouter");
+    bindgen("UniswapV3QuoterV2");
 
     bindgen("UniswapV2Factory");
     bindgen("UniswapV3Factory");
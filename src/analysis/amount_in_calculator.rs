@@ -3,24 +3,21 @@ use std::error::Error;
 use ethers::types::U256;
 use serde_json::Value;
 
-use crate::{models::config_models::NetworkMetadata, errors::SendableError, trading::balance::{get_token_balance, get_native_balance}};
-
-const MIN_WALLET_BALANCE_THRESHOLD: f64 = 0.1; // Minimum balance in wallet to consider trading (e.g., 0.1 wrapped tokens)
-const MIN_LIQUIDITY_THRESHOLD: f64 = 1000.0; // Minimum liquidity in the pool to consider trading
-const MIN_CONFIDENCE_THRESHOLD: f64 = 50.0; // Minimum confidence score to proceed with the trade
-const MIN_TRADE_AMOUNT: f64 = 0.0; // Minimum amount to trade, could be set to a small number instead of 0
+use crate::{models::config_models::NetworkMetadata, errors::SendableError, trading::balance::get_native_balance};
 
 pub async fn calculate_amount_in(
     network_metadata: &NetworkMetadata,
     confidence_score: f64, 
     liquidity: f64
 ) -> Result<f64, SendableError> {
+    let sizing = &network_metadata.scoring_policy.trade_sizing;
+
     // Safety checks
-    if liquidity < MIN_LIQUIDITY_THRESHOLD || confidence_score < MIN_CONFIDENCE_THRESHOLD {
-        return Ok(MIN_TRADE_AMOUNT); // Not enough liquidity or confidence, return minimum trade amount
+    if liquidity < sizing.min_liquidity || confidence_score < sizing.min_confidence {
+        return Ok(sizing.min_trade_amount); // Not enough liquidity or confidence, return minimum trade amount
     }
-    let max_wallet_trade_percentage = 0.50; // 50% of wallet balance
-    let max_price_impact = 0.01; // 1% maximum price impact
+    let max_wallet_trade_percentage = sizing.max_wallet_trade_percentage;
+    let max_price_impact = sizing.max_price_impact;
 
     // Correctly await the async function and handle the Result
     let max_safe_trade_amount = calculate_max_safe_trade_amount(liquidity, max_price_impact, &network_metadata.native_coin_coingecko_id).await
@@ -35,8 +32,8 @@ pub async fn calculate_amount_in(
     let eth_precision = U256::exp10(18); // Represents 10^18 for conversion
     let wallet_balance_eth = wallet_balance_wei.as_u128() as f64 / eth_precision.as_u128() as f64;
     // Ensure wallet balance is above the minimum threshold
-    if wallet_balance_eth < MIN_WALLET_BALANCE_THRESHOLD {
-        return Ok(MIN_TRADE_AMOUNT); // Not enough balance, return minimum trade amount
+    if wallet_balance_eth < sizing.min_wallet_balance {
+        return Ok(sizing.min_trade_amount); // Not enough balance, return minimum trade amount
     }
 
     // Calculate the maximum trade amount based on wallet balance
@@ -0,0 +1,349 @@
+use std::{sync::Arc, error::Error};
+
+use chrono::Utc;
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    providers::{Provider, Http, Middleware, RawCall, RpcError},
+    types::{
+        spoof, transaction::eip2718::TypedTransaction, Address, BlockId, BlockNumber, Bytes,
+        TransactionRequest, H256, U256,
+    },
+    utils::{keccak256, parse_ether},
+};
+
+use crate::{
+    bindings::{
+        erc20::{AllowanceCall, BalanceOfCall},
+        uniswap_v2_router02::{
+            UniswapV2Router02, SwapExactETHForTokensSupportingFeeOnTransferTokensCall,
+            SwapExactTokensForTokensSupportingFeeOnTransferTokensCall,
+        },
+    },
+    models::{config_models::{NetworkMetadata, ExchangeConfig}, security_models::HoneypotSimulation},
+    utils::formatter::u256_to_f64,
+};
+
+const SIMULATION_AMOUNT_IN: &str = "0.01"; // Native amount spent by the simulated buy
+const SIMULATION_WALLET_BALANCE: &str = "1000"; // Native balance given to the wallet for the simulation
+const DEADLINE_SECS: i64 = 15 * 60; // 15 minutes
+const MAX_STORAGE_SLOT: u64 = 20; // Highest slot probed for the token's balance and allowance mappings
+const SEARCH_STEPS: u32 = 14; // Binary search steps, measures the taxes to ~0.01%
+
+// Simulates a buy through the UniswapV2Router02 and an immediate sell back to the wrapped native token, through
+// `base_token` when the pair was not launched against the wrapped native, with
+// eth_call state overrides, nothing is signed or sent. The supporting-fee router variants check the amount that
+// actually reached the recipient against amountOutMin, so the received amounts are found by searching for the
+// highest amountOutMin that does not revert. Every call runs against the same block, which keeps the result
+// reproducible on a local fork or a node serving recorded state (see simulationRpcUrl).
+pub async fn simulate_round_trip(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    token_address: Address,
+    base_token: Address,
+) -> Result<HoneypotSimulation, Box<dyn Error>> {
+    let rpc_url = network_metadata.simulation_rpc_url.as_deref().unwrap_or(&network_metadata.rpc_url);
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+    let block: BlockId = BlockNumber::Number(provider.get_block_number().await?).into();
+
+    let simulation = Simulation {
+        provider: provider.clone(),
+        block,
+        router: exchange.router_contract_address,
+        token: token_address,
+        wrapped_native: network_metadata.wrapped_native_address,
+        base_token,
+        wallet: network_metadata.wallet_address,
+        deadline: U256::from(Utc::now().timestamp() + DEADLINE_SECS),
+    };
+    let router = UniswapV2Router02::new(simulation.router, provider);
+
+    // Buy
+    let amount_in = parse_ether(SIMULATION_AMOUNT_IN)?;
+    let buy_path = simulation.buy_path();
+    let expected_tokens = *router.get_amounts_out(amount_in, buy_path).block(block).call().await?.last().ok_or("Empty amounts")?;
+
+    let Some(tokens_received) = highest_passing(expected_tokens, |min_out| simulation.buy(amount_in, min_out)).await? else {
+        return Ok(HoneypotSimulation { buy_failed: true, sell_failed: false, buy_tax: None, sell_tax: None });
+    };
+    let buy_tax = tax(expected_tokens, tokens_received);
+
+    // Sell what the buy delivered, the wallet is credited and the router approved through storage overrides
+    let sell_state = simulation.sell_state(tokens_received).await?;
+    let sell_path = simulation.sell_path();
+    let expected_native = *router.get_amounts_out(tokens_received, sell_path).block(block).call().await?.last().ok_or("Empty amounts")?;
+
+    let Some(native_received) = highest_passing(expected_native, |min_out| simulation.sell(&sell_state, tokens_received, min_out)).await? else {
+        return Ok(HoneypotSimulation { buy_failed: false, sell_failed: true, buy_tax: Some(buy_tax), sell_tax: None });
+    };
+
+    Ok(HoneypotSimulation {
+        buy_failed: false,
+        sell_failed: false,
+        buy_tax: Some(buy_tax),
+        sell_tax: Some(tax(expected_native, native_received)),
+    })
+}
+
+struct Simulation {
+    provider: Arc<Provider<Http>>,
+    block: BlockId,
+    router: Address,
+    token: Address,
+    wrapped_native: Address,
+    base_token: Address,
+    wallet: Address,
+    deadline: U256,
+}
+
+impl Simulation {
+    fn buy_path(&self) -> Vec<Address> {
+        if self.base_token == self.wrapped_native {
+            vec![self.wrapped_native, self.token]
+        } else {
+            vec![self.wrapped_native, self.base_token, self.token]
+        }
+    }
+
+    fn sell_path(&self) -> Vec<Address> {
+        self.buy_path().into_iter().rev().collect()
+    }
+
+    async fn buy(&self, amount_in: U256, amount_out_min: U256) -> Result<bool, Box<dyn Error>> {
+        let data = SwapExactETHForTokensSupportingFeeOnTransferTokensCall {
+            amount_out_min,
+            path: self.buy_path(),
+            to: self.wallet,
+            deadline: self.deadline,
+        }.encode();
+        let state = spoof::balance(self.wallet, parse_ether(SIMULATION_WALLET_BALANCE)?);
+        self.succeeds(self.router, data, Some(amount_in), &state).await
+    }
+
+    async fn sell(&self, state: &spoof::State, amount_in: U256, amount_out_min: U256) -> Result<bool, Box<dyn Error>> {
+        // Selling for the wrapped token keeps the wallet out of the native transfer, only the token's own rules apply
+        let data = SwapExactTokensForTokensSupportingFeeOnTransferTokensCall {
+            amount_in,
+            amount_out_min,
+            path: self.sell_path(),
+            to: self.wallet,
+            deadline: self.deadline,
+        }.encode();
+        self.succeeds(self.router, data, None, state).await
+    }
+
+    // State override crediting the wallet with the tokens and approving the router to spend them
+    async fn sell_state(&self, amount: U256) -> Result<spoof::State, Box<dyn Error>> {
+        let mut balance_key = None;
+        'slots: for slot in 0..=MAX_STORAGE_SLOT {
+            for key in mapping_keys(self.wallet, slot) {
+                let state = spoof::storage(self.token, key, word(amount));
+                if self.read(BalanceOfCall { owner: self.wallet }.encode(), &state).await? == Some(amount) {
+                    balance_key = Some(key);
+                    break 'slots;
+                }
+            }
+        }
+        let balance_key = balance_key.ok_or("Token balance storage slot not found")?;
+
+        for slot in 0..=MAX_STORAGE_SLOT {
+            for owner_key in mapping_keys(self.wallet, slot) {
+                let allowance_key = allowance_key(owner_key, self.router);
+                let mut state = spoof::storage(self.token, balance_key, word(amount));
+                state.account(self.token).store(allowance_key, word(U256::MAX));
+                let allowance = self.read(AllowanceCall { owner: self.wallet, spender: self.router }.encode(), &state).await?;
+                if allowance == Some(U256::MAX) {
+                    return Ok(state);
+                }
+            }
+        }
+        Err("Token allowance storage slot not found".into())
+    }
+
+    // Reads a uint256 view of the token, None when the call reverts
+    async fn read(&self, data: Vec<u8>, state: &spoof::State) -> Result<Option<U256>, Box<dyn Error>> {
+        let tx: TypedTransaction = TransactionRequest::new().to(self.token).data(data).into();
+        match self.provider.call_raw(&tx).block(self.block).state(state).await {
+            Ok(output) => Ok(U256::decode(output).ok()),
+            Err(e) if e.as_error_response().is_some() => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    // Whether the call goes through, a revert is an answer while a transport error is not
+    async fn succeeds(&self, to: Address, data: Vec<u8>, value: Option<U256>, state: &spoof::State) -> Result<bool, Box<dyn Error>> {
+        let mut tx_request = TransactionRequest::new().from(self.wallet).to(to).data(Bytes::from(data));
+        if let Some(value) = value {
+            tx_request = tx_request.value(value);
+        }
+        let tx: TypedTransaction = tx_request.into();
+        match self.provider.call_raw(&tx).block(self.block).state(state).await {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_error_response().is_some() => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+// Highest value in [0, upper] for which the call succeeds, None when it fails even at 0
+async fn highest_passing<F, Fut>(upper: U256, call: F) -> Result<Option<U256>, Box<dyn Error>>
+where
+    F: Fn(U256) -> Fut,
+    Fut: std::future::Future<Output = Result<bool, Box<dyn Error>>>,
+{
+    if !call(U256::zero()).await? {
+        return Ok(None);
+    }
+    if call(upper).await? {
+        return Ok(Some(upper));
+    }
+
+    let (mut low, mut high) = (U256::zero(), upper);
+    for _ in 0..SEARCH_STEPS {
+        let middle = low + (high - low) / 2;
+        if call(middle).await? {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    Ok(Some(low))
+}
+
+// Storage keys of `owner` in a mapping at `slot`, with Solidity and Vyper key ordering
+fn mapping_keys(owner: Address, slot: u64) -> [H256; 2] {
+    let owner = owner.encode();
+    let slot = U256::from(slot).encode();
+    [
+        H256::from(keccak256([owner.clone(), slot.clone()].concat())),
+        H256::from(keccak256([slot, owner].concat())),
+    ]
+}
+
+// Storage key of `spender` in the inner mapping of allowance[owner][spender], given the owner's key in the outer one
+fn allowance_key(owner_key: H256, spender: Address) -> H256 {
+    H256::from(keccak256([spender.encode(), owner_key.encode()].concat()))
+}
+
+fn word(value: U256) -> H256 {
+    H256::from_slice(&value.encode())
+}
+
+// Share of the expected amount that did not arrive, between 0.0 and 1.0
+fn tax(expected: U256, received: U256) -> f64 {
+    if expected.is_zero() {
+        return 0.0;
+    }
+    (1.0 - u256_to_f64(received) / u256_to_f64(expected)).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Two 32-byte words, the address and the slot both left-padded, built by hand rather than through the encoder
+    fn padded(address: Address, slot: u8) -> Vec<u8> {
+        let mut bytes = vec![0u8; 64];
+        bytes[12..32].copy_from_slice(address.as_bytes());
+        bytes[63] = slot;
+        bytes
+    }
+
+    #[test]
+    fn mapping_keys_follow_the_solidity_and_vyper_layouts() {
+        let owner: Address = "0x000000000000000000000000000000000000dEaD".parse().unwrap();
+        let [solidity, vyper] = mapping_keys(owner, 3);
+
+        let bytes = padded(owner, 3);
+        assert_eq!(solidity, H256::from(keccak256(&bytes)));
+        assert_eq!(vyper, H256::from(keccak256([&bytes[32..], &bytes[..32]].concat())));
+        assert_ne!(mapping_keys(owner, 3), mapping_keys(owner, 4));
+    }
+
+    #[test]
+    fn allowance_key_hashes_the_spender_with_the_owner_key() {
+        let owner: Address = "0x000000000000000000000000000000000000dEaD".parse().unwrap();
+        let spender: Address = "0x7a250d5630B4cF539739dF2C011C8dD0F4125488".parse().unwrap();
+        let [owner_key, _] = mapping_keys(owner, 1);
+
+        let mut bytes = padded(spender, 0);
+        bytes[32..].copy_from_slice(owner_key.as_bytes());
+        assert_eq!(allowance_key(owner_key, spender), H256::from(keccak256(&bytes)));
+    }
+
+    #[test]
+    fn word_is_the_big_endian_value() {
+        assert_eq!(word(U256::one()), H256::from_low_u64_be(1));
+        assert_eq!(word(U256::MAX), H256::repeat_byte(0xff));
+    }
+
+    #[test]
+    fn tax_is_the_missing_share() {
+        assert_eq!(tax(U256::from(1_000), U256::from(1_000)), 0.0);
+        assert!((tax(U256::from(1_000), U256::from(950)) - 0.05).abs() < 1e-9);
+        assert_eq!(tax(U256::from(1_000), U256::from(2_000)), 0.0); // More than quoted is not a negative tax
+        assert_eq!(tax(U256::zero(), U256::zero()), 0.0);
+    }
+
+    async fn search(upper: U256, threshold: Option<U256>) -> Option<U256> {
+        highest_passing(upper, |value| async move { Ok(threshold.is_some_and(|threshold| value <= threshold)) }).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn search_stops_at_the_bounds() {
+        let upper = U256::exp10(18);
+        assert_eq!(search(upper, None).await, None); // Fails even at 0, the swap itself reverts
+        assert_eq!(search(upper, Some(upper)).await, Some(upper));
+        assert_eq!(search(upper, Some(upper * 2)).await, Some(upper));
+    }
+
+    #[tokio::test]
+    async fn search_never_overshoots_and_lands_within_its_precision() {
+        let upper = U256::exp10(18);
+        for threshold in [U256::zero(), U256::one(), upper * 95 / 100, upper / 3, upper - 1] {
+            let found = search(upper, Some(threshold)).await.unwrap();
+            assert!(found <= threshold, "{} is above {}", found, threshold);
+            assert!(threshold - found <= upper >> SEARCH_STEPS, "{} is too far below {}", found, threshold);
+        }
+    }
+
+    #[tokio::test]
+    async fn search_propagates_call_errors() {
+        let result = highest_passing(U256::from(100), |_| async { Err::<bool, Box<dyn Error>>("transport".into()) }).await;
+        assert!(result.is_err());
+    }
+
+    // Needs an Ethereum mainnet node or fork, e.g. `anvil --fork-url <mainnet rpc>` and
+    // SIMULATION_RPC_URL=http://127.0.0.1:8545 cargo test -- --ignored
+    #[tokio::test]
+    #[ignore]
+    async fn round_trip_against_a_mainnet_fork() {
+        let rpc_url = std::env::var("SIMULATION_RPC_URL").expect("SIMULATION_RPC_URL is not set");
+        let weth: Address = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2".parse().unwrap();
+        let dai: Address = "0x6B175474E89094C44Da98b954EedeAC495271d0F".parse().unwrap();
+        let network_metadata: NetworkMetadata = serde_json::from_value(json!({
+            "name": "Ethereum",
+            "symbol": "ETH",
+            "chainId": 1,
+            "nativeCoinCoingeckoId": "ethereum",
+            "rpcUrl": "http://unused",
+            "wsUrl": "ws://unused",
+            "walletAddress": "0x000000000000000000000000000000000000dEaD",
+            "wrappedNativeAddress": weth,
+            "simulationRpcUrl": rpc_url,
+        })).unwrap();
+        let exchange: ExchangeConfig = serde_json::from_value(json!({
+            "name": "Uniswap V2",
+            "baseImplementation": "UniswapV2",
+            "factoryContractAddress": "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+            "routerContractAddress": "0x7a250d5630B4cF539739dF2C011C8dD0F4125488",
+        })).unwrap();
+
+        // DAI keeps balances and allowances in plain Solidity mappings and takes no fee, both legs go through untaxed
+        let simulation = simulate_round_trip(&network_metadata, &exchange, dai, weth).await.unwrap();
+        assert!(!simulation.buy_failed);
+        assert!(!simulation.sell_failed);
+        assert!(simulation.buy_tax.unwrap() < 0.001);
+        assert!(simulation.sell_tax.unwrap() < 0.001);
+    }
+}
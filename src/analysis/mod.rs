@@ -2,3 +2,4 @@ pub mod processor;
 pub mod security_checker;
 pub mod score_calculator;
 pub mod amount_in_calculator;
+pub mod honeypot_simulator;
@@ -1,88 +1,184 @@
 use std::error::Error;
 
-use ethers::types::H160;
+use ethers::{types::{Address, U256}, utils::{parse_ether, format_ether}};
 
-use crate::{models::{config_models::{NetworkMetadata, ExchangeConfig}, processed_trade::{ProcessedTrade, TradeSubStatus}}, analysis::security_checker::assess_token_security, trading::buy::buy_token, listeners::{swaps_listener::listen_to_swaps, mempool_swap_listener::listen_to_mempool_swaps}};
+use crate::{models::{config_models::{NetworkMetadata, ExchangeConfig}, processed_trade::{ProcessedTrade, TradeSubStatus}, security_models::TransferTax}, dex::{adapter_for, DexAdapter, NewPool}, analysis::security_checker::assess_token_security, trading::{buy::buy_token, router::{base_hop, pair_base, TokenRoute}, position_manager::{manage_position, OpenPosition}}, listeners::{swaps_listener::listen_to_swaps, mempool_swap_listener::listen_to_mempool_swaps, liquidity_listener::wait_for_liquidity}};
 
-const YOUR_CONFIDENCE_THRESHOLD: f64 = 70.0;
-
-pub async fn process_pair(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, token_0: &H160, token_1: &H160, pair_or_pool: &H160) -> Result<(), Box<dyn Error>> {
-    // Check if wrapped native address is neither token_0 nor token_1
+pub async fn process_pair(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, new_pool: &NewPool) -> Result<(), Box<dyn Error>> {
+    let pair_or_pool = &new_pool.pool;
     let exchange_name = exchange.name.clone();
-    if *token_0 != network_metadata.wrapped_native_address && *token_1 != network_metadata.wrapped_native_address {
+
+    // Determine which token to assess, the other side must be the wrapped native or one of the network's base tokens
+    let Some((token_to_assess, base_token)) = pair_base(network_metadata, new_pool.token_0, new_pool.token_1) else {
         log::info!(
-            "[{} - {}] Pair or pool does not involve the wrapped native token. This might indicate it's not a new liquidity pool with the native token as a base.",
+            "[{} - {}] Pair or pool does not pair a new token with the wrapped native or a base token. This might indicate it's not a new liquidity pool.",
             network_metadata.name, exchange_name
         );
         return Ok(());
-    }
-    
-    // Determine which token to assess
-    let token_to_assess = if *token_0 == network_metadata.wrapped_native_address { token_1 } else { token_0 };
-    
-    log::info!("[{} - {} - {}] Processing pair... Pair/Pool address: {}", network_metadata.name, exchange_name, token_to_assess, pair_or_pool);
+    };
+    let token_to_assess = &token_to_assess;
+
+    // Pairs launched against another base are bought native -> base -> token
+    let adapter = adapter_for(exchange)?;
+    let via = match base_hop(network_metadata, adapter.as_ref(), base_token).await {
+        Ok(via) => via,
+        Err(e) => {
+            log::warn!("[{} - {} - {}] Cannot route the native token to base {:?}: {}", network_metadata.name, exchange_name, token_to_assess, base_token, e);
+            return Ok(());
+        },
+    };
+    let route = TokenRoute { pool: *pair_or_pool, fee: new_pool.fee, via };
+
+    log::info!(
+        "[{} - {} - {}] Processing pair... Pair/Pool address: {}, fee tier: {:?}, tick spacing: {:?}",
+        network_metadata.name, exchange_name, token_to_assess, pair_or_pool, new_pool.fee, new_pool.tick_spacing,
+    );
     let mut trade = ProcessedTrade::new(
-        network_metadata.chain_id.clone(),
+        network_metadata.chain_id.to_string(),
         network_metadata.name.clone(),
         exchange.name.clone(),
         *pair_or_pool, // Use the pair_or_pool address as the pair_address
-        *token_0,     // Use token_0 address as the token_address
-        network_metadata.wrapped_native_address, // Use the wrapped native address as the base_token_address
+        *token_to_assess, // Use the assessed token as the token_address
+        base_token, // Use the token the pair was launched against as the base_token_address
+        new_pool.fee,
     )?;
-    
-    // Safety checks
-    let assessment = assess_token_security(network_metadata, &exchange_name, token_to_assess).await?;
-    log::info!("[{} - {} - {}]  Confidence Score: {}", network_metadata.name, exchange_name, token_to_assess, assessment.confidence_score,);
-    log::info!("[{} - {} - {}]  Recommended Trade Amount: {}", network_metadata.name, exchange_name, token_to_assess, assessment.recommended_trade_amount);
 
+    // Any failure before the buy went through leaves nothing to manage, the trade must not stay an open position
+    let bought = match buy_pair(network_metadata, exchange, adapter.as_ref(), &mut trade, *token_to_assess, base_token, route).await {
+        Ok(bought) => bought,
+        Err(e) => {
+            trade.canceled(TradeSubStatus::BuyFailed)?;
+            return Err(e);
+        },
+    };
+    let Some((amount_in_wei, amount_tokens, transfer_tax)) = bought else {
+        return Ok(());
+    };
+
+    let position = OpenPosition {
+        trade,
+        token_address: *token_to_assess,
+        route,
+        amount_in_wei,
+        amount_tokens,
+        transfer_tax,
+    };
+
+    // Spawn a new task that sells the position once an exit rule fires
     let network_metadata_clone = network_metadata.clone();
     let exchange_clone = exchange.clone();
-    let token_to_assess_clone = *token_to_assess;
-
-    if assessment.confidence_score >= YOUR_CONFIDENCE_THRESHOLD {
-        // Call the buy function with cloned data
-        let buy_result = buy_token(
-            &exchange_clone,
-            &network_metadata_clone,
-            token_to_assess_clone,
-            assessment.recommended_trade_amount,
-            exchange_clone.base_implementation == "UniswapV3",
-            None
-        ).await;
-
-        match buy_result {
-            Ok(tx_hash) => {
-                log::info!("Successfully bought the token. Transaction hash: {:?}", tx_hash);
-                // trade.open_position(TradeSubStatus::FailedSecurityCheck);
-                // Spawn a new task for listen_to_swaps with cloned data
-                // Clone data for the first listener
-                let network_metadata_clone1 = network_metadata.clone();
-                let exchange_clone1 = exchange.clone();
-                let token_to_assess_clone1 = *token_to_assess;
-                let pair_or_pool_clone1 = *pair_or_pool;
-
-                // Spawn a new task for listen_to_swaps
-                tokio::spawn(async move {
-                    listen_to_swaps(&network_metadata_clone1, &exchange_clone1, token_to_assess_clone1, pair_or_pool_clone1).await;
-                });
-
-                // Clone data for the second listener
-                let network_metadata_clone2 = network_metadata.clone();
-                let exchange_clone2 = exchange.clone();
-                let token_to_assess_clone2 = *token_to_assess;
-                let pair_or_pool_clone2 = *pair_or_pool;
-
-                // Spawn a new task for listen_to_mempool_swaps
-                tokio::spawn(async move {
-                    listen_to_mempool_swaps(&network_metadata_clone2, &exchange_clone2, token_to_assess_clone2, pair_or_pool_clone2).await;
-                });
-            },
-            Err(e) => log::error!("Failed to buy the token: {}", e),
+    tokio::spawn(async move {
+        manage_position(network_metadata_clone, exchange_clone, position).await;
+    });
+
+    // Clone data for the swaps listener
+    let network_metadata_clone1 = network_metadata.clone();
+    let exchange_clone1 = exchange.clone();
+    let token_to_assess_clone1 = *token_to_assess;
+    let pair_or_pool_clone1 = *pair_or_pool;
+
+    // Spawn a new task for listen_to_swaps
+    tokio::spawn(async move {
+        listen_to_swaps(&network_metadata_clone1, &exchange_clone1, token_to_assess_clone1, pair_or_pool_clone1).await;
+    });
+
+    // Clone data for the mempool listener
+    let network_metadata_clone2 = network_metadata.clone();
+    let exchange_clone2 = exchange.clone();
+    let token_to_assess_clone2 = *token_to_assess;
+    let pair_or_pool_clone2 = *pair_or_pool;
+
+    // Spawn a new task for listen_to_mempool_swaps
+    tokio::spawn(async move {
+        if let Err(e) = listen_to_mempool_swaps(&network_metadata_clone2, &exchange_clone2, token_to_assess_clone2, pair_or_pool_clone2).await {
+            log::error!(
+                "[{} - {} - {}] Mempool swap listener stopped: {}",
+                network_metadata_clone2.name, exchange_clone2.name, token_to_assess_clone2, e,
+            );
         }
-    } else {
-        trade.canceled(TradeSubStatus::FailedSecurityCheck);
+    });
+
+    Ok(())
+}
+
+// Waits for liquidity, assesses the token and buys it. Returns what was paid, the tokens received and the
+// transfer tax of the token, None when the trade was skipped
+async fn buy_pair(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    adapter: &dyn DexAdapter,
+    trade: &mut ProcessedTrade,
+    token: Address,
+    base_token: Address,
+    route: TokenRoute,
+) -> Result<Option<(U256, U256, TransferTax)>, Box<dyn Error>> {
+    let exchange_name = &exchange.name;
+
+    // Pools are usually created before any liquidity is added, there is nothing to assess until it arrives
+    match wait_for_liquidity(network_metadata, exchange, adapter, token, &route).await? {
+        Some(depth) => log::info!("[{} - {} - {}] Liquidity arrived: {} native", network_metadata.name, exchange_name, token, depth),
+        None => {
+            trade.canceled(TradeSubStatus::LiquidityTimeout)?;
+            log::warn!("No liquidity was added to the pool in time. Skipping trade.");
+            return Ok(None);
+        },
+    }
+
+    // Safety checks
+    let assessment = assess_token_security(network_metadata, exchange, &token, &base_token).await?;
+    log::info!("[{} - {} - {}]  Confidence Score: {}", network_metadata.name, exchange_name, token, assessment.confidence_score,);
+    log::info!("[{} - {} - {}]  Recommended Trade Amount: {}", network_metadata.name, exchange_name, token, assessment.recommended_trade_amount);
+    trade.scored(assessment.score_breakdown.clone())?;
+
+    if assessment.confidence_score < network_metadata.scoring_policy.buy_threshold {
+        trade.canceled(TradeSubStatus::FailedSecurityCheck)?;
         log::warn!("Confidence score is too low. Skipping trade.");
+        return Ok(None);
     }
 
-    Ok(())
-}
\ No newline at end of file
+    if assessment.honeypot_simulation.as_ref().is_some_and(|simulation| simulation.is_honeypot()) {
+        trade.canceled(TradeSubStatus::FailedHoneypotSimulation)?;
+        log::warn!("Simulated round trip failed, token looks like a honeypot. Skipping trade.");
+        return Ok(None);
+    }
+
+    // A taxed token could be bought but never sold back on exchanges without fee-on-transfer swaps
+    if assessment.transfer_tax.is_taxed() && !adapter.supports_fee_on_transfer() {
+        trade.canceled(TradeSubStatus::UnsupportedTransferTax)?;
+        log::warn!("Token has a transfer tax and {} cannot swap fee-on-transfer tokens. Skipping trade.", exchange_name);
+        return Ok(None);
+    }
+
+    let amount_in_wei = parse_ether(assessment.recommended_trade_amount.to_string().as_str())?;
+    let execution = match buy_token(
+        exchange,
+        network_metadata,
+        token,
+        Some(route),
+        assessment.recommended_trade_amount,
+        assessment.transfer_tax,
+    ).await {
+        Ok(execution) => execution,
+        Err(e) => {
+            trade.canceled(TradeSubStatus::BuyFailed)?;
+            log::error!("Failed to buy the token: {}", e);
+            return Ok(None);
+        },
+    };
+
+    trade.record_replacements(&execution.replacements)?;
+    if execution.canceled {
+        trade.canceled(TradeSubStatus::TransactionCanceled)?;
+        log::warn!("Buy was not included in time and got canceled. Transaction hash: {:?}", execution.tx_hash);
+        return Ok(None);
+    }
+
+    log::info!("Successfully bought the token. Transaction hash: {:?}, simulated: {}", execution.tx_hash, execution.simulated);
+
+    // What reached the wallet according to the receipt, a simulated fill only has the quoted amount
+    let amount_tokens = execution.amount_out.unwrap_or(execution.quote.amount_out);
+    let gas_fee_paid: f64 = format_ether(execution.gas_fee).parse()?;
+    trade.open_position(assessment.recommended_trade_amount, Some(assessment.confidence_score), gas_fee_paid, execution.simulated)?;
+
+    Ok(Some((amount_in_wei, amount_tokens, assessment.transfer_tax)))
+}
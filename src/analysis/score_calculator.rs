@@ -1,246 +1,276 @@
-use crate::{models::token_api_response_models::TokenSecurityDetails, utils::formatter::{parse_f64_field, parse_i32_field}};
-
-const HIGH_RISK_COEFF: f64 = 5.0; // High-risk issues have a significant impact on the score.
-const RISKY_COEFF: f64 = 3.0;     // Risky issues have a moderate impact.
-const LOW_RISK_COEFF: f64 = 1.0;
-
-pub fn calculate_security_score(token_info: &TokenSecurityDetails) -> f64 {
-    let high_risk_score = apply_high_risk_checks(token_info) * HIGH_RISK_COEFF;
-
-    println!("high_risk_score: {}", high_risk_score);
-    if high_risk_score == 0.0 {
-        return 0.0;
-    }
-
-    let risky_score = apply_risky_checks(token_info) * RISKY_COEFF;
-    println!("risky_score: {}", risky_score);
-    let low_risk_score = apply_low_risk_checks(token_info) * LOW_RISK_COEFF;
-    println!("low_risk_score: {}", low_risk_score);
-    // Calculate the final score as a weighted average or sum of the individual scores
-    let final_score = (high_risk_score + risky_score + low_risk_score) / (HIGH_RISK_COEFF + RISKY_COEFF + LOW_RISK_COEFF);
-    println!("final_score: {}", final_score);
-    final_score.max(0.0).min(100.0) // Ensure it doesn't go below 0
+use crate::{
+    models::{
+        token_api_response_models::TokenSecurityDetails,
+        scoring_policy_models::{ScoringPolicy, CategoryPolicy, CheckRule},
+        security_models::{ScoreBreakdown, FiredCheck},
+    },
+    utils::formatter::parse_f64_field,
+};
+
+// What the token security details report for a check
+enum Signal {
+    Flag(Option<bool>), // Some(true) when the risky value is reported, None when missing
+    Value(Option<f64>), // None when missing
 }
 
-fn apply_high_risk_checks(token_info: &TokenSecurityDetails) -> f64 {
-    let mut high_risk_score: f64 = 100.0; // Start with a perfect score for high-risk checks
+pub fn calculate_security_score(token_info: &TokenSecurityDetails, policy: &ScoringPolicy) -> ScoreBreakdown {
+    let mut breakdown = ScoreBreakdown::default();
 
-    // Check if the token is listed in any DEX.
-    if token_info.is_in_dex.as_deref() != Some("1") {
-        return 0.0; 
+    breakdown.high_risk_score = apply_category_checks(token_info, "highRisk", &policy.high_risk, &mut breakdown);
+    if breakdown.veto_reason.is_none() && breakdown.high_risk_score == 0.0 {
+        breakdown.veto_reason = Some("highRisk score reached 0".to_string());
     }
-    
-    // Check if can buy and sell
-    match token_info.cannot_buy.as_deref() {
-        Some("1") => return 0.0, // Extremely risky, set high-risk score to 0 immediately.
-        None => high_risk_score -= 30.0, // Heavy penalty for missing information.
-        _ => (), // No change for other cases.
+    if breakdown.veto_reason.is_some() {
+        return breakdown;
     }
 
-    match token_info.cannot_sell_all.as_deref() {
-        Some("1") => return 0.0, // Extremely risky, set high-risk score to 0 immediately.
-        None => high_risk_score -= 30.0, // Heavy penalty for missing information.
-        _ => (), // No change for other cases.
+    breakdown.risky_score = apply_category_checks(token_info, "risky", &policy.risky, &mut breakdown);
+    breakdown.low_risk_score = apply_category_checks(token_info, "lowRisk", &policy.low_risk, &mut breakdown);
+    if breakdown.veto_reason.is_some() {
+        return breakdown;
     }
 
-    // Initialize liquidity as 0.0
-    let mut liquidity = 0.0;
-
-    // Process only if the dex list exists and contains exactly one element
-    match &token_info.dex {
-        Some(dex_list) if dex_list.len() == 1 => {
-            let dex = &dex_list[0];  // Safely access the first and only dex entry
+    // Calculate the final score as a weighted average of the individual scores
+    let total_weight = policy.high_risk.weight + policy.risky.weight + policy.low_risk.weight;
+    let final_score = (breakdown.high_risk_score * policy.high_risk.weight
+        + breakdown.risky_score * policy.risky.weight
+        + breakdown.low_risk_score * policy.low_risk.weight) / total_weight;
+    breakdown.final_score = final_score.clamp(0.0, 100.0);
+    breakdown
+}
 
-            if dex.liquidity.is_some() {
-                // Attempt to parse the liquidity string to a float
-                liquidity = dex.liquidity.as_ref().unwrap().parse::<f64>().unwrap_or(0.0);
-            }
-        },
-        _ => {
-            // Either dex doesn't exist or doesn't have exactly one entry
-            return 0.0; 
+// Category score between 0 and 100, 0 when a veto check fired
+fn apply_category_checks(token_info: &TokenSecurityDetails, category_name: &str, category: &CategoryPolicy, breakdown: &mut ScoreBreakdown) -> f64 {
+    let mut score: f64 = 100.0; // Start with a perfect score for the category
+
+    for (name, rule) in category.checks.iter().filter(|(_, rule)| rule.enabled) {
+        let Some(signal) = check_signal(token_info, name) else {
+            continue; // Unknown checks are rejected when the config is loaded
+        };
+        let (fired, penalty) = evaluate(&signal, rule);
+        let veto = fired && rule.veto;
+        if !veto && penalty == 0.0 {
+            continue;
         }
-    }
 
-    if liquidity == 0.0 {
-        return 0.0;
-    }
-
-    // Honeypot Check
-    match token_info.is_honeypot.as_deref() {
-        Some("1") => return 0.0, // Extremely risky, set high-risk score to 0 immediately.
-        None => high_risk_score -= 30.0, // Heavy penalty for missing information.
-        _ => (), // No change for other cases.
-    }
-
-    // Same Creator as Known Honeypot Check
-    if token_info.honeypot_with_same_creator.as_deref() == Some("1") {
-        return 0.0; // Extremely risky if it has the same creator as a known honeypot.
-    }
-
-    // Self-Destruct Check
-    if token_info.selfdestruct.as_deref() == Some("1") {
-        return 0.0; // Self-destruct feature is a critical risk.
-    }
-
-    // Hidden Owner Check
-    if token_info.hidden_owner.as_deref() == Some("1") {
-        return 0.0; // Hidden owner suggests a lack of transparency and potential for malicious activity.
-    }
-
-    // Owner Can Change Balance Check
-    if token_info.owner_change_balance.as_deref() == Some("1") {
-        return 0.0; // If the owner can arbitrarily change balances, it's a significant risk.
+        breakdown.fired_checks.push(FiredCheck {
+            category: category_name.to_string(),
+            check: name.clone(),
+            observed: observed(&signal),
+            penalty: if veto { score } else { penalty },
+            veto,
+        });
+        if veto {
+            breakdown.veto_reason = Some(format!("{}.{} fired", category_name, name));
+            return 0.0;
+        }
+        score -= penalty;
     }
 
-    // Proxy Contract Check
-    if token_info.is_proxy.as_deref() == Some("1") {
-        high_risk_score -= 50.0; // Proxy contracts add complexity and potential risks.
-    }
+    score.clamp(0.0, 100.0)
+}
 
-    // Mintable Token Check
-    if token_info.is_mintable.as_deref() == Some("1") {
-        high_risk_score -= 50.0; // Mintable tokens can lead to inflation and devaluation.
+// Whether the check fired and the penalty it applies, a negative penalty is a bonus
+fn evaluate(signal: &Signal, rule: &CheckRule) -> (bool, f64) {
+    match signal {
+        Signal::Flag(None) | Signal::Value(None) => (false, rule.missing_penalty),
+        Signal::Flag(Some(flagged)) => (*flagged, if *flagged { rule.penalty } else { 0.0 }),
+        Signal::Value(Some(value)) => {
+            let fired = rule.above.is_some_and(|above| *value > above) || rule.below.is_some_and(|below| *value < below);
+            if fired {
+                (true, rule.penalty)
+            } else {
+                (false, value * rule.scale - rule.pass_bonus)
+            }
+        },
     }
-
-    // Ensure the high-risk score doesn't go below 0
-    high_risk_score.max(0.0)
 }
 
-fn apply_risky_checks(token_info: &TokenSecurityDetails) -> f64 {
-    let mut risky_score: f64 = 100.0; // Start with a perfect score for risky checks
-
-    // Liquidity Check
-    if let Some(dex_list) = &token_info.dex {
-        if let Some(dex) = dex_list.get(0) {
-            let liquidity = parse_f64_field(&dex.liquidity);
-            risky_score -= if liquidity < 20000.0 { 20.0 } else { -5.0 };
-        } else {
-            risky_score -= 5.0; // Penalty for no DEX info
-        }
-    } else {
-        risky_score -= 5.0; // Penalty for missing DEX info
-    }
+fn check_signal(token_info: &TokenSecurityDetails, name: &str) -> Option<Signal> {
+    let signal = match name {
+        // Tokens not listed in any DEX, or without exactly one DEX holding liquidity, cannot be traded safely
+        "notInDex" => Signal::Flag(Some(token_info.is_in_dex.as_deref() != Some("1"))),
+        "noLiquidity" => Signal::Flag(Some(single_dex_liquidity(token_info) == 0.0)),
+        "cannotBuy" => flag(&token_info.cannot_buy),
+        "cannotSellAll" => flag(&token_info.cannot_sell_all),
+        "isHoneypot" => flag(&token_info.is_honeypot),
+        "honeypotWithSameCreator" => flag(&token_info.honeypot_with_same_creator),
+        "selfdestruct" => flag(&token_info.selfdestruct),
+        "hiddenOwner" => flag(&token_info.hidden_owner),
+        "ownerChangeBalance" => flag(&token_info.owner_change_balance),
+        "isProxy" => flag(&token_info.is_proxy),
+        "isMintable" => flag(&token_info.is_mintable),
+        "isAntiWhale" => flag(&token_info.is_anti_whale),
+        "slippageModifiable" => flag(&token_info.slippage_modifiable),
+        "isBlacklisted" => flag(&token_info.is_blacklisted),
+        "transferPausable" => flag(&token_info.transfer_pausable),
+        "tradingCooldown" => flag(&token_info.trading_cooldown),
+        "closedSource" => Signal::Flag(token_info.is_open_source.as_deref().map(|value| value == "0")),
+        "externalCall" => flag(&token_info.external_call),
+        "personalSlippageModifiable" => flag(&token_info.personal_slippage_modifiable),
+        "isWhitelisted" => flag(&token_info.is_whitelisted),
+        "antiWhaleModifiable" => flag(&token_info.anti_whale_modifiable),
+
+        "liquidity" => Signal::Value(
+            token_info.dex.as_ref().and_then(|dex_list| dex_list.first()).map(|dex| parse_f64_field(&dex.liquidity)),
+        ),
+        "buyTax" => value(&token_info.buy_tax),
+        "sellTax" => value(&token_info.sell_tax),
+        "ownerPercent" => value(&token_info.owner_percent),
+        "holderCount" => value(&token_info.holder_count),
+        "topHolderPercent" => Signal::Value(token_info.holders.as_ref().map(|holders| {
+            holders.iter()
+                .filter_map(|holder| holder.percent.as_ref().and_then(|p| p.parse::<f64>().ok()))
+                .fold(0.0, |max, x| x.max(max)) // Find the max percentage
+        })),
+        _ => return None,
+    };
+    Some(signal)
+}
 
-    // Buy Tax Check
-    if let Some(_) = &token_info.buy_tax {
-        let buy_tax = parse_f64_field(&token_info.buy_tax);  // Pass the entire Option<String>
-        risky_score -= buy_tax * 100.0; // Decrease score based on buy tax percentage
+fn observed(signal: &Signal) -> Option<String> {
+    match signal {
+        Signal::Flag(flagged) => flagged.map(|flagged| if flagged { "flagged" } else { "clear" }.to_string()),
+        Signal::Value(value) => value.map(|value| value.to_string()),
     }
+}
 
-    // Sell Tax Check
-    if let Some(_) = &token_info.sell_tax {
-        let sell_tax = parse_f64_field(&token_info.sell_tax);  // Pass the entire Option<String>
-        risky_score -= sell_tax * 100.0; // Decrease score based on sell tax percentage
-    }
+fn flag(field: &Option<String>) -> Signal {
+    Signal::Flag(field.as_deref().map(|value| value == "1"))
+}
 
-    // Anti Whale Mechanism Check
-    match token_info.is_anti_whale.as_deref() {
-        Some("1") => risky_score -= 10.0, // Presence of anti-whale mechanisms can be risky.
-        None => risky_score -= 5.0,       // Penalty for missing information.
-        _ => (),
-    }
+fn value(field: &Option<String>) -> Signal {
+    Signal::Value(field.as_ref().map(|_| parse_f64_field(field)))
+}
 
-    // Modifiable Tax Rate Check
-    match token_info.slippage_modifiable.as_deref() {
-        Some("1") => risky_score -= 15.0, // Modifiable tax rates introduce unpredictability.
-        None => risky_score -= 5.0,       // Penalty for missing information.
-        _ => (),
+// Liquidity of the only DEX listing the token, 0.0 when there is none or more than one
+fn single_dex_liquidity(token_info: &TokenSecurityDetails) -> f64 {
+    match &token_info.dex {
+        Some(dex_list) if dex_list.len() == 1 => dex_list[0].liquidity.as_deref()
+            .and_then(|liquidity| liquidity.parse::<f64>().ok())
+            .unwrap_or(0.0),
+        _ => 0.0,
     }
+}
 
-    // Owner Concentration Check
-    if let Some(_) = &token_info.owner_percent {
-        let owner_percent = parse_f64_field(&token_info.owner_percent);  // Pass the entire Option<String>
-        risky_score -= if owner_percent > 50.0 { 30.0 } else { owner_percent / 2.0 }; // More concentration, higher penalty.
-    }
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
 
-    // Blacklisted Functionality Check
-    match token_info.is_blacklisted.as_deref() {
-        Some("1") => risky_score -= 20.0, // Blacklist functionality can be risky.
-        None => risky_score -= 5.0,       // Penalty for missing information.
-        _ => (),
-    }
+    use super::*;
 
-    // Transfer Pausable Check
-    match token_info.transfer_pausable.as_deref() {
-        Some("1") => risky_score -= 10.0, // Ability to pause transfers is risky.
-        None => risky_score -= 5.0,       // Penalty for missing information.
-        _ => (),
+    fn token(details: serde_json::Value) -> TokenSecurityDetails {
+        serde_json::from_value(details).unwrap()
     }
 
-    // Trading Cooldown Check
-    match token_info.trading_cooldown.as_deref() {
-        Some("1") => risky_score -= 10.0, // Trading cooldowns can be risky.
-        None => risky_score -= 5.0,       // Penalty for missing information.
-        _ => (),
+    fn category(weight: f64, checks: serde_json::Value) -> CategoryPolicy {
+        serde_json::from_value(json!({ "weight": weight, "checks": checks })).unwrap()
     }
 
-    // Ensure score doesn't go below 0
-    risky_score.max(0.0).min(100.0)
-}
-
-fn apply_low_risk_checks(token_info: &TokenSecurityDetails) -> f64 {
-    let mut low_risk_score: f64 = 100.0; // Start with a perfect low-risk score
-
-    // Proxy Contract Check
-    match token_info.is_proxy.as_deref() {
-        Some("1") => low_risk_score -= 5.0, // Small penalty for being a proxy
-        None => low_risk_score -= 2.0,      // Minor penalty for missing information
-        _ => (),                            // No change for other cases.
+    fn policy(high_risk: CategoryPolicy, risky: CategoryPolicy, low_risk: CategoryPolicy) -> ScoringPolicy {
+        ScoringPolicy { high_risk, risky, low_risk, ..ScoringPolicy::default() }
     }
 
-    // Open Source Check
-    match token_info.is_open_source.as_deref() {
-        Some("0") => low_risk_score -= 5.0, // Small penalty for closed source
-        None => low_risk_score -= 2.0,      // Minor penalty for missing information
-        _ => (),
+    #[test]
+    fn final_score_is_the_weighted_average_of_the_categories() {
+        let policy = policy(
+            category(1.0, json!({})),
+            category(1.0, json!({ "isBlacklisted": { "penalty": 50.0 } })),
+            category(2.0, json!({})),
+        );
+        let breakdown = calculate_security_score(&token(json!({ "is_blacklisted": "1" })), &policy);
+        assert_eq!(breakdown.risky_score, 50.0);
+        assert_eq!(breakdown.final_score, 87.5); // (100 + 50 + 2 * 100) / 4
+        assert!(breakdown.veto_reason.is_none());
     }
 
-    // External Call Check
-    match token_info.external_call.as_deref() {
-        Some("1") => low_risk_score -= 5.0, // Small penalty for external calls
-        None => low_risk_score -= 2.0,      // Minor penalty for missing information
-        _ => (),
+    #[test]
+    fn veto_short_circuits_the_other_categories() {
+        let policy = policy(
+            category(5.0, json!({ "isHoneypot": { "veto": true }, "isMintable": { "penalty": 10.0 } })),
+            category(3.0, json!({ "isBlacklisted": { "penalty": 50.0 } })),
+            category(1.0, json!({})),
+        );
+        let breakdown = calculate_security_score(&token(json!({ "is_honeypot": "1", "is_mintable": "1", "is_blacklisted": "1" })), &policy);
+        assert_eq!(breakdown.veto_reason.as_deref(), Some("highRisk.isHoneypot fired"));
+        assert_eq!(breakdown.final_score, 0.0);
+        assert_eq!(breakdown.risky_score, 0.0); // Never evaluated
+        // The veto takes the whole category score, the checks after it are not evaluated
+        assert_eq!(breakdown.fired_checks.len(), 1);
+        assert_eq!(breakdown.fired_checks[0].penalty, 100.0);
+        assert!(breakdown.fired_checks[0].veto);
     }
 
-    // Personal Slippage Modifiable Check
-    match token_info.personal_slippage_modifiable.as_deref() {
-        Some("1") => low_risk_score -= 5.0, // Small penalty for modifiable slippage
-        None => low_risk_score -= 2.0,      // Minor penalty for missing information
-        _ => (),
+    #[test]
+    fn high_risk_score_of_zero_vetoes() {
+        let policy = policy(
+            category(5.0, json!({ "isProxy": { "penalty": 60.0 }, "isMintable": { "penalty": 60.0 } })),
+            category(3.0, json!({})),
+            category(1.0, json!({})),
+        );
+        let breakdown = calculate_security_score(&token(json!({ "is_proxy": "1", "is_mintable": "1" })), &policy);
+        assert_eq!(breakdown.high_risk_score, 0.0);
+        assert_eq!(breakdown.veto_reason.as_deref(), Some("highRisk score reached 0"));
+        assert_eq!(breakdown.final_score, 0.0);
     }
 
-    // Holder Count Check
-    if let Some(holder_count_str) = &token_info.holder_count {
-        let holder_count = holder_count_str.parse::<i32>().unwrap_or(0);
-        low_risk_score -= if holder_count < 10 { 5.0 } else { 0.0 }; // Small penalty for very few holders
-    } else {
-        low_risk_score -= 2.0; // Minor penalty for missing information
+    #[test]
+    fn missing_values_apply_the_missing_penalty() {
+        let policy = policy(
+            category(1.0, json!({ "cannotBuy": { "veto": true, "missingPenalty": 30.0 } })),
+            category(1.0, json!({ "ownerPercent": { "above": 50.0, "penalty": 30.0, "missingPenalty": 5.0 } })),
+            category(1.0, json!({})),
+        );
+        let breakdown = calculate_security_score(&token(json!({})), &policy);
+        // A missing flag does not fire its veto
+        assert!(breakdown.veto_reason.is_none());
+        assert_eq!(breakdown.high_risk_score, 70.0);
+        assert_eq!(breakdown.risky_score, 95.0);
+        assert!(breakdown.fired_checks.iter().all(|check| check.observed.is_none() && !check.veto));
     }
 
-    // Whitelist Functionality Check
-    match token_info.is_whitelisted.as_deref() {
-        Some("1") => low_risk_score -= 5.0, // Small penalty for having a whitelist
-        None => low_risk_score -= 2.0,      // Minor penalty for missing information
-        _ => (),
+    #[test]
+    fn value_checks_fire_outside_their_bounds() {
+        let policy = policy(
+            category(1.0, json!({})),
+            category(1.0, json!({ "ownerPercent": { "above": 50.0, "below": 1.0, "penalty": 30.0, "scale": 0.5 } })),
+            category(1.0, json!({})),
+        );
+        let score = |owner_percent: &str| calculate_security_score(&token(json!({ "owner_percent": owner_percent })), &policy).risky_score;
+        assert_eq!(score("60"), 70.0);
+        assert_eq!(score("0.5"), 70.0);
+        // Within the bounds the value is scaled
+        assert_eq!(score("20"), 90.0);
+        assert_eq!(score("50"), 75.0);
     }
 
-    // Anti Whale Modifiable Check
-    match token_info.anti_whale_modifiable.as_deref() {
-        Some("1") => low_risk_score -= 5.0, // Small penalty for modifiable anti-whale measures
-        None => low_risk_score -= 2.0,      // Minor penalty for missing information
-        _ => (),
+    #[test]
+    fn pass_bonus_is_capped_at_a_perfect_score() {
+        let policy = policy(
+            category(1.0, json!({})),
+            category(1.0, json!({
+                "liquidity": { "below": 20000.0, "penalty": 20.0, "passBonus": 5.0 },
+                "isBlacklisted": { "penalty": 3.0 },
+            })),
+            category(1.0, json!({})),
+        );
+        let details = token(json!({ "dex": [{ "liquidity": "50000" }], "is_blacklisted": "1" }));
+        let breakdown = calculate_security_score(&details, &policy);
+        assert_eq!(breakdown.risky_score, 100.0); // 100 + 5 - 3, clamped
+        let liquidity = breakdown.fired_checks.iter().find(|check| check.check == "liquidity").unwrap();
+        assert_eq!(liquidity.penalty, -5.0);
+        assert_eq!(liquidity.observed.as_deref(), Some("50000"));
     }
 
-    // Top Holder Concentration Check
-    if let Some(holders) = &token_info.holders {
-        let top_holder_percent = holders.iter()
-            .filter_map(|holder| holder.percent.as_ref().and_then(|p| p.parse::<f64>().ok()))
-            .fold(0.0, |max, x| x.max(max)); // Find the max percentage
-        
-        low_risk_score -= if top_holder_percent > 50.0 { 5.0 } else { 0.0 }; // Small penalty if one holder has more than 50%
+    #[test]
+    fn disabled_checks_are_skipped() {
+        let policy = policy(
+            category(1.0, json!({ "isHoneypot": { "veto": true, "enabled": false } })),
+            category(1.0, json!({})),
+            category(1.0, json!({})),
+        );
+        let breakdown = calculate_security_score(&token(json!({ "is_honeypot": "1" })), &policy);
+        assert!(breakdown.veto_reason.is_none());
+        assert_eq!(breakdown.final_score, 100.0);
     }
-
-    // Normalize the low-risk score to a scale of 0-100 and ensure it doesn't go below 0
-    low_risk_score.max(0.0).min(100.0)
-}
\ No newline at end of file
+}
@@ -3,48 +3,65 @@ use ethers::types::H160;
 use tokio::time::sleep;
 
 use crate::{
-    models::{config_models::NetworkMetadata, token_api_response_models::{TokenSecurityDetails, TokenSecurityResponse}, security_models::TokenAssessment},
+    models::{config_models::{NetworkMetadata, ExchangeConfig}, token_api_response_models::{TokenSecurityDetails, TokenSecurityResponse}, security_models::{TokenAssessment, HoneypotSimulation, TransferTax}},
     errors::SendableError,
+    utils::formatter::parse_f64_field,
 };
 
-use super::{score_calculator::calculate_security_score, amount_in_calculator::calculate_amount_in};
+use super::{score_calculator::calculate_security_score, amount_in_calculator::calculate_amount_in, honeypot_simulator::simulate_round_trip};
 
-pub async fn assess_token_security(network_metadata: &NetworkMetadata, exchange_name: &str, token_address: &H160) -> Result<TokenAssessment, SendableError> {
+// `base_token` is the token the pair was launched against, the honeypot simulation trades through it
+pub async fn assess_token_security(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, token_address: &H160, base_token: &H160) -> Result<TokenAssessment, SendableError> {
     const MAX_RETRIES: usize = 3;
     const BASE_BACKOFF: u64 = 5; // seconds
 
     for attempt in 0..MAX_RETRIES {
-        match fetch_and_assess_token(network_metadata, exchange_name, token_address).await {
+        match fetch_and_assess_token(network_metadata, exchange, token_address, base_token).await {
             Ok(assessment) 
-            if assessment.confidence_score >= 70.0 => {
+            if assessment.confidence_score >= network_metadata.scoring_policy.buy_threshold => {
                 // If the confidence score is acceptable, return the assessment immediately
-                println!("Acceptable confidence score of {} achieved, breaking loop.", assessment.confidence_score);
+                log::info!(
+                    "[{} - {} - {}] Acceptable confidence score of {} achieved.",
+                    network_metadata.name, exchange.name, token_address, assessment.confidence_score,
+                );
                 return Ok(assessment);
             }
             Ok(_) | Err(_) if attempt < MAX_RETRIES - 1 => {
                 // If the confidence score is too low or an error occurred, retry after a delay
                 let backoff = BASE_BACKOFF * 2_u64.pow(attempt as u32); // Exponential backoff
-                println!("Retrying token assessment due to low confidence score or error. Attempt {} of {}", attempt + 1, MAX_RETRIES);
+                log::info!(
+                    "[{} - {} - {}] Retrying token assessment due to low confidence score or error. Attempt {} of {}",
+                    network_metadata.name, exchange.name, token_address, attempt + 1, MAX_RETRIES,
+                );
                 sleep(Duration::from_secs(backoff)).await;
             }
             _ => {
                 // If we've reached the maximum number of retries or got an unexpected result, break and return the last result.
-                println!("Maximum retries exceeded or an unexpected result received. Returning the last result.");
+                log::warn!(
+                    "[{} - {} - {}] Maximum retries exceeded or an unexpected result received. Returning the last result.",
+                    network_metadata.name, exchange.name, token_address,
+                );
                 break;
             }
         }
     }
 
     // Last attempt after retries exhausted.
-    fetch_and_assess_token(network_metadata, exchange_name, token_address).await
+    fetch_and_assess_token(network_metadata, exchange, token_address, base_token).await
 }
 
-async fn fetch_and_assess_token(network_metadata: &NetworkMetadata, exchange_name: &str, token_address: &H160) -> Result<TokenAssessment, SendableError> {
+async fn fetch_and_assess_token(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, token_address: &H160, base_token: &H160) -> Result<TokenAssessment, SendableError> {
+    let exchange_name = exchange.name.as_str();
 
-    let token_info = fetch_token_security_info(&network_metadata.chain_id, token_address).await.map_err(SendableError::from)?;
+    let token_info = fetch_token_security_info(network_metadata.chain_id, token_address).await?;
     
     // Calculate the security score based on various factors
-    let confidence_score = calculate_security_score(&token_info);
+    let score_breakdown = calculate_security_score(&token_info, &network_metadata.scoring_policy);
+    let confidence_score = score_breakdown.final_score;
+    log::info!(
+        "[{} - {} - {}] Score breakdown: {}",
+        network_metadata.name, exchange_name, token_address, serde_json::to_string(&score_breakdown).unwrap_or_default(),
+    );
 
     let mut liquidity = 0.0;
 
@@ -65,7 +82,10 @@ async fn fetch_and_assess_token(network_metadata: &NetworkMetadata, exchange_nam
             // Consider setting a lower confidence score because of missing or multiple DEX entries
             return Ok(TokenAssessment {
                 confidence_score: 0.0, // or some other value that indicates reduced confidence
+                score_breakdown,
                 recommended_trade_amount: 0.0,
+                honeypot_simulation: None,
+                transfer_tax: transfer_tax(&token_info, None),
             });
         }
     }
@@ -73,22 +93,69 @@ async fn fetch_and_assess_token(network_metadata: &NetworkMetadata, exchange_nam
     if liquidity == 0.0 {
         return Ok(TokenAssessment {
             confidence_score: 0.0, // or some other value that indicates reduced confidence
+            score_breakdown,
             recommended_trade_amount: 0.0,
+            honeypot_simulation: None,
+            transfer_tax: transfer_tax(&token_info, None),
         });
     }
 
     
     // Now, we need to await the result of calculate_amount_in since it's async
     let recommended_trade_amount = calculate_amount_in(network_metadata, confidence_score, liquidity).await?;
-    
+
+    // Check the API's verdict against a simulated buy and sell
+    let honeypot_simulation = simulate_honeypot(network_metadata, exchange, token_address, base_token).await;
+
+    let transfer_tax = transfer_tax(&token_info, honeypot_simulation.as_ref());
+    if transfer_tax.is_taxed() {
+        log::info!("[{} - {} - {}] Transfer tax: {:?}", network_metadata.name, exchange_name, token_address, transfer_tax);
+    }
+
     // Return the assessment
     Ok(TokenAssessment {
         confidence_score,
+        score_breakdown,
         recommended_trade_amount, // Replace with actual calculation
+        honeypot_simulation,
+        transfer_tax,
     })
 }
 
-async fn fetch_token_security_info(network_chain_id: &str, token_address: &H160) -> Result<TokenSecurityDetails, SendableError> {
+// Higher of the API's and the simulated taxes, so a tax is never underestimated
+fn transfer_tax(token_info: &TokenSecurityDetails, simulation: Option<&HoneypotSimulation>) -> TransferTax {
+    // Below this the simulated tax is rounding from the amount search
+    const MIN_TRANSFER_TAX: f64 = 0.001;
+    let tax = |api_tax: &Option<String>, simulated_tax: Option<f64>| {
+        let tax = parse_f64_field(api_tax).max(simulated_tax.unwrap_or_default()).clamp(0.0, 1.0);
+        if tax < MIN_TRANSFER_TAX { 0.0 } else { tax }
+    };
+
+    TransferTax {
+        buy: tax(&token_info.buy_tax, simulation.and_then(|simulation| simulation.buy_tax)),
+        sell: tax(&token_info.sell_tax, simulation.and_then(|simulation| simulation.sell_tax)),
+    }
+}
+
+async fn simulate_honeypot(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, token_address: &H160, base_token: &H160) -> Option<HoneypotSimulation> {
+    // The simulation swaps through the UniswapV2Router02 interface
+    if exchange.base_implementation != "UniswapV2" {
+        return None;
+    }
+
+    match simulate_round_trip(network_metadata, exchange, *token_address, *base_token).await {
+        Ok(simulation) => {
+            log::info!("[{} - {} - {}] Honeypot simulation: {:?}", network_metadata.name, exchange.name, token_address, simulation);
+            Some(simulation)
+        },
+        Err(e) => {
+            log::warn!("[{} - {} - {}] Honeypot simulation inconclusive: {}", network_metadata.name, exchange.name, token_address, e);
+            None
+        },
+    }
+}
+
+async fn fetch_token_security_info(network_chain_id: u64, token_address: &H160) -> Result<TokenSecurityDetails, SendableError> {
     let token_address_str = format!("{:#x}", token_address);
     let api_url = format!("https://api.gopluslabs.io/api/v1/token_security/{}?contract_addresses={}", network_chain_id, token_address_str);
 
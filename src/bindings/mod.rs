@@ -1,7 +1,10 @@
+#![allow(clippy::module_inception)] // Abigen nests each binding in a module of the same name
+
 pub mod erc20;
 pub mod uniswap_v2_factory;
 pub mod uniswap_v3_factory;
 pub mod uniswap_v2_router02;
 pub mod uniswap_v3_smart_router;
+pub mod uniswap_v3_quoter_v2;
 pub mod uniswap_v2_pair;
 pub mod uniswap_v3_pool;
\ No newline at end of file
@@ -0,0 +1,371 @@
+use std::{sync::Arc, error::Error, path::Path};
+
+use ethers::{
+    providers::{Provider, Http},
+    types::{Address, U256},
+    utils::{format_ether, format_units},
+};
+
+use crate::{
+    analysis::security_checker::assess_token_security,
+    bindings::erc20::Erc20,
+    config::{load_config, watcher::watch_config},
+    dex::adapter_for,
+    listeners::listener_manager::ListenerManager,
+    models::{config_models::{Config, NetworkConfig, NetworkMetadata, ExchangeConfig}, processed_trade::{ProcessedTrade, TradeStatus, TradeSubStatus}, security_models::{TokenAssessment, TransferTax}},
+    network::signer::verify_signer,
+    storage::{csv_import::import_legacy_csv, init_trade_store, trade_store, SqliteTradeStore, TradeQuery, TradeStore, TRADES_DB_PATH},
+    trading::{
+        allowance::{ensure_allowance, record_spent},
+        balance::{get_native_balance, get_token_balance},
+        buy::buy_token,
+        executor::SwapExecution,
+        position_manager::{manage_position, record_sale, resume_position},
+        router::find_route,
+        sell::{min_sell_output, sell_token},
+    },
+};
+
+pub async fn run(config_path: &Path) -> Result<(), Box<dyn Error>> {
+    let config = load_config(config_path)?;
+    verify_signers(&config)?;
+    println!("Loaded configuration {}.", config_path.display());
+
+    // Open the trade store and bring in the trades of the old CSV ledger on first start
+    let trade_store = open_trade_store()?;
+    import_legacy_csv(trade_store.as_ref())?;
+    let open_trades = trade_store.find(&TradeQuery { status: Some(TradeStatus::OpenPosition), ..Default::default() })?;
+    log::info!("Trade store ready, {} positions left open.", open_trades.len());
+    resume_positions(&config, open_trades);
+
+    // One listener per exchange, kept in line with the config file while the bot runs
+    let mut listeners = ListenerManager::default();
+    listeners.apply(&config);
+    watch_config(config_path, |config| match verify_signers(&config) {
+        Ok(()) => listeners.apply(&config),
+        Err(e) => log::error!("Configuration {} not applied: {}", config_path.display(), e),
+    }).await;
+
+    Ok(())
+}
+
+pub async fn assess(config_path: &Path, chain: &str, token: Address, exchange: Option<&str>, base: Option<Address>) -> Result<(), Box<dyn Error>> {
+    let config = load_config(config_path)?;
+    let network = find_network(&config, chain)?;
+    let exchange = find_exchange(network, exchange)?;
+    let network_metadata = &network.metadata;
+
+    // The honeypot simulation trades through the base of the token's pool
+    let base_token = match base {
+        Some(base) => base,
+        None => {
+            let adapter = adapter_for(exchange)?;
+            match find_route(network_metadata, adapter.as_ref(), token).await {
+                Ok(route) => route.via.map_or(network_metadata.wrapped_native_address, |hop| hop.token),
+                Err(e) => {
+                    log::warn!("{}, assessing against the wrapped native token.", e);
+                    network_metadata.wrapped_native_address
+                },
+            }
+        },
+    };
+
+    let assessment = assess_token_security(network_metadata, exchange, &token, &base_token).await?;
+    println!("Token {:?} on {} ({}), base {:?}", token, network_metadata.name, exchange.name, base_token);
+    print_assessment(&assessment, network_metadata.scoring_policy.buy_threshold);
+    Ok(())
+}
+
+pub async fn buy(config_path: &Path, chain: &str, token: Address, amount: f64, exchange: Option<&str>, tax: f64) -> Result<(), Box<dyn Error>> {
+    let config = load_config(config_path)?;
+    let network = find_network(&config, chain)?;
+    let exchange = find_exchange(network, exchange)?;
+    let network_metadata = &network.metadata;
+    verify_signer(network_metadata)?;
+    let adapter = adapter_for(exchange)?;
+    open_trade_store()?;
+
+    // Recorded like the bot's own trades, so the position is listed and managed by `run`
+    let route = find_route(network_metadata, adapter.as_ref(), token).await?;
+    let mut trade = ProcessedTrade::new(
+        network_metadata.chain_id.to_string(),
+        network_metadata.name.clone(),
+        exchange.name.clone(),
+        route.pool,
+        token,
+        route.via.map_or(network_metadata.wrapped_native_address, |hop| hop.token),
+        route.fee,
+    )?;
+
+    let transfer_tax = TransferTax { buy: tax, sell: 0.0 };
+    let execution = match buy_token(exchange, network_metadata, token, Some(route), amount, transfer_tax).await {
+        Ok(execution) => execution,
+        Err(e) => {
+            trade.canceled(TradeSubStatus::BuyFailed)?;
+            return Err(e);
+        },
+    };
+    trade.record_replacements(&execution.replacements)?;
+    if execution.canceled {
+        trade.canceled(TradeSubStatus::TransactionCanceled)?;
+    } else {
+        let gas_fee_paid: f64 = format_ether(execution.gas_fee).parse()?;
+        trade.open_position(amount, None, gas_fee_paid, execution.simulated)?;
+    }
+    print_execution("Buy", &execution);
+    Ok(())
+}
+
+pub async fn sell(config_path: &Path, chain: &str, token: Address, amount: Option<U256>, exchange: Option<&str>, tax: f64) -> Result<(), Box<dyn Error>> {
+    let config = load_config(config_path)?;
+    let network = find_network(&config, chain)?;
+    let exchange = find_exchange(network, exchange)?;
+    let network_metadata = &network.metadata;
+    verify_signer(network_metadata)?;
+    let adapter = adapter_for(exchange)?;
+    open_trade_store()?;
+
+    let whole_balance = amount.is_none();
+    let amount = match amount {
+        Some(amount) => amount,
+        None => get_token_balance(&network_metadata.rpc_url, network_metadata.wallet_address, token).await?,
+    };
+    if amount.is_zero() {
+        return Err(format!("Nothing to sell, the wallet holds no {:?}", token).into());
+    }
+
+    let route = find_route(network_metadata, adapter.as_ref(), token).await?;
+    let transfer_tax = TransferTax { buy: 0.0, sell: tax };
+    let amount_out_min = min_sell_output(adapter.as_ref(), exchange, network_metadata, token, amount, &route, transfer_tax).await?;
+
+    // The router can only swap the tokens once it is allowed to spend them
+    let spender = adapter.router_address();
+    if let Some(approval) = ensure_allowance(network_metadata, token, spender, amount).await? {
+        println!("Approved {:?} to spend {} of the token. Transaction hash: {:?}", spender, approval.amount, approval.tx_hash);
+    }
+
+    let execution = sell_token(exchange.clone(), network_metadata.clone(), token, amount, amount_out_min, route, transfer_tax).await?;
+    if !execution.simulated && !execution.canceled {
+        record_spent(network_metadata, token, spender, amount);
+    }
+    print_execution("Sell", &execution);
+    record_manual_sale(network_metadata, exchange, token, whole_balance, &execution)
+}
+
+// Closes the open position the sale emptied, partial sales leave it open
+fn record_manual_sale(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    token: Address,
+    whole_balance: bool,
+    execution: &SwapExecution,
+) -> Result<(), Box<dyn Error>> {
+    let trade_store = open_trade_store()?;
+    let query = TradeQuery { status: Some(TradeStatus::OpenPosition), network_chain_id: Some(network_metadata.chain_id.to_string()), ..Default::default() };
+    let open_trades = trade_store.find(&query)?
+        .into_iter()
+        .filter(|trade| trade.token_address == token && trade.exchange_name.eq_ignore_ascii_case(&exchange.name));
+    for mut trade in open_trades {
+        trade.record_replacements(&execution.replacements)?;
+        if execution.canceled {
+            continue;
+        }
+        if !whole_balance {
+            println!("Position #{} stays open, only part of the balance was sold.", trade.id.unwrap_or_default());
+            continue;
+        }
+        record_sale(&mut trade, execution)?;
+        println!("Position #{} closed.", trade.id.unwrap_or_default());
+    }
+    Ok(())
+}
+
+// Reads the trade store only, no configuration is needed
+pub fn positions(chain: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let trade_store = SqliteTradeStore::open(TRADES_DB_PATH)?;
+    let chain_id = chain.filter(|chain| chain.parse::<u64>().is_ok());
+    let query = TradeQuery { status: Some(TradeStatus::OpenPosition), network_chain_id: chain_id.map(str::to_string), ..Default::default() };
+    let trades: Vec<_> = trade_store.find(&query)?
+        .into_iter()
+        .filter(|trade| chain_id.is_some() || chain.is_none_or(|name| trade.network_name.eq_ignore_ascii_case(name)))
+        .collect();
+
+    if trades.is_empty() {
+        println!("No open positions.");
+        return Ok(());
+    }
+    for trade in &trades {
+        println!(
+            "#{} {} - {} token {:?} pair {:?}: bought {} native, score {}, {}{}updated {}",
+            trade.id.unwrap_or_default(),
+            trade.network_name,
+            trade.exchange_name,
+            trade.token_address,
+            trade.pair_address,
+            trade.amount_bought.map_or("-".to_string(), |amount| amount.to_string()),
+            trade.security_score.map_or("-".to_string(), |score| format!("{:.2}", score)),
+            if trade.simulated { "simulated, " } else { "" },
+            if matches!(trade.substatus, Some(TradeSubStatus::CloseFailed)) { "close failed, " } else { "" },
+            trade.last_update,
+        );
+    }
+    println!("{} open positions.", trades.len());
+    Ok(())
+}
+
+pub async fn balances(config_path: &Path, chain: &str, tokens: &[Address]) -> Result<(), Box<dyn Error>> {
+    let config = load_config(config_path)?;
+    let network_metadata = &find_network(&config, chain)?.metadata;
+    let rpc_url = network_metadata.rpc_url.as_str();
+    let wallet = network_metadata.wallet_address;
+
+    let native_balance = get_native_balance(rpc_url, wallet).await?;
+    println!("Wallet {:?} on {}", wallet, network_metadata.name);
+    println!("  {} {}", format_ether(native_balance), network_metadata.symbol);
+
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+    let base_tokens = network_metadata.base_tokens.iter().map(|base| base.address);
+    for token in base_tokens.chain(tokens.iter().copied()) {
+        let balance = get_token_balance(rpc_url, wallet, token).await?;
+        let erc20 = Erc20::new(token, provider.clone());
+        let symbol = erc20.symbol().call().await.unwrap_or_else(|_| format!("{:?}", token));
+        // Tokens without decimals are shown in their smallest unit
+        match erc20.decimals().call().await {
+            Ok(decimals) => println!("  {} {}", format_units(balance, u32::from(decimals))?, symbol),
+            Err(_) => println!("  {} {} (smallest unit)", balance, symbol),
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_config(config_path: &Path) -> Result<(), Box<dyn Error>> {
+    let config = load_config(config_path)?;
+    let exchanges = config.networks.iter().map(|network| network.exchanges.len()).sum::<usize>();
+    println!("Configuration {} is valid: {} networks, {} exchanges.", config_path.display(), config.networks.len(), exchanges);
+    Ok(())
+}
+
+// Manages the positions left open by an earlier run again, except the ones already given up on
+fn resume_positions(config: &Config, open_trades: Vec<ProcessedTrade>) {
+    for mut trade in open_trades {
+        // Trades stored by failed buys or imported from the old ledger were never bought, there is nothing to sell
+        if trade.amount_bought.is_none_or(|amount_bought| amount_bought <= 0.0) {
+            log::warn!("Trade #{} on token {:?} has no recorded buy, marking it canceled.", trade.id.unwrap_or_default(), trade.token_address);
+            if let Err(e) = trade.canceled(TradeSubStatus::BuyFailed) {
+                log::error!("Failed to cancel trade #{}: {}", trade.id.unwrap_or_default(), e);
+            }
+            continue;
+        }
+        if matches!(trade.substatus, Some(TradeSubStatus::CloseFailed)) {
+            log::warn!("Position #{} on token {:?} failed to close earlier, sell it by hand.", trade.id.unwrap_or_default(), trade.token_address);
+            continue;
+        }
+        let exchange = find_network(config, &trade.network_chain_id)
+            .and_then(|network| Ok((network.metadata.clone(), find_exchange(network, Some(&trade.exchange_name))?.clone())));
+        let (network_metadata, exchange) = match exchange {
+            Ok(found) => found,
+            Err(e) => {
+                log::error!("Cannot resume position #{} on token {:?}: {}", trade.id.unwrap_or_default(), trade.token_address, e);
+                continue;
+            },
+        };
+
+        tokio::spawn(async move {
+            let trade_id = trade.id.unwrap_or_default();
+            let position = match resume_position(&network_metadata, &exchange, trade).await {
+                Ok(position) => position,
+                Err(e) => {
+                    log::error!("[{} - {}] Cannot resume position #{}: {}", network_metadata.name, exchange.name, trade_id, e);
+                    return;
+                },
+            };
+            manage_position(network_metadata, exchange, position).await;
+        });
+    }
+}
+
+// Opens the trade store once per process, trades persist themselves through it
+fn open_trade_store() -> Result<Arc<dyn TradeStore>, Box<dyn Error>> {
+    if let Ok(trade_store) = trade_store() {
+        return Ok(trade_store);
+    }
+    let trade_store: Arc<dyn TradeStore> = Arc::new(SqliteTradeStore::open(TRADES_DB_PATH)?);
+    init_trade_store(trade_store.clone())?;
+    Ok(trade_store)
+}
+
+// Loads the signer of every network, reporting all the mismatches at once
+fn verify_signers(config: &Config) -> Result<(), Box<dyn Error>> {
+    let errors: Vec<String> = config.networks.iter()
+        .filter_map(|network| verify_signer(&network.metadata).err().map(|e| e.to_string()))
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid signers:\n{}", errors.join("\n")).into())
+    }
+}
+
+// Matches a chain id or a network name, ignoring case
+fn find_network<'c>(config: &'c Config, chain: &str) -> Result<&'c NetworkConfig, Box<dyn Error>> {
+    config.networks.iter()
+        .find(|network| network.metadata.chain_id.to_string() == chain || network.metadata.name.eq_ignore_ascii_case(chain))
+        .ok_or_else(|| {
+            let known: Vec<_> = config.networks.iter().map(|network| format!("{} ({})", network.metadata.name, network.metadata.chain_id)).collect();
+            format!("No network \"{}\" in the configuration, known networks: {}", chain, known.join(", ")).into()
+        })
+}
+
+// The first exchange of the network when no name is given
+fn find_exchange<'n>(network: &'n NetworkConfig, name: Option<&str>) -> Result<&'n ExchangeConfig, Box<dyn Error>> {
+    let exchange = match name {
+        Some(name) => network.exchanges.iter().find(|exchange| exchange.name.eq_ignore_ascii_case(name)),
+        None => network.exchanges.first(),
+    };
+    exchange.ok_or_else(|| {
+        let known: Vec<_> = network.exchanges.iter().map(|exchange| exchange.name.as_str()).collect();
+        format!("No exchange \"{}\" on {}, known exchanges: {}", name.unwrap_or_default(), network.metadata.name, known.join(", ")).into()
+    })
+}
+
+fn print_assessment(assessment: &TokenAssessment, buy_threshold: f64) {
+    let breakdown = &assessment.score_breakdown;
+    println!("Confidence score: {:.2} (buy threshold {:.2})", assessment.confidence_score, buy_threshold);
+    println!(
+        "Category scores: high risk {:.1}, risky {:.1}, low risk {:.1}",
+        breakdown.high_risk_score, breakdown.risky_score, breakdown.low_risk_score,
+    );
+    if let Some(veto_reason) = &breakdown.veto_reason {
+        println!("Vetoed: {}", veto_reason);
+    }
+    if !breakdown.fired_checks.is_empty() {
+        println!("Checks fired:");
+        for check in &breakdown.fired_checks {
+            println!(
+                "  [{}] {} = {}, penalty {:.1}{}",
+                check.category, check.check, check.observed.as_deref().unwrap_or("missing"), check.penalty, if check.veto { ", veto" } else { "" },
+            );
+        }
+    }
+    println!("Transfer tax: buy {:.2}%, sell {:.2}%", assessment.transfer_tax.buy * 100.0, assessment.transfer_tax.sell * 100.0);
+    match &assessment.honeypot_simulation {
+        Some(simulation) if simulation.is_honeypot() => {
+            println!("Honeypot simulation: failed (buy failed: {}, sell failed: {})", simulation.buy_failed, simulation.sell_failed);
+        },
+        Some(_) => println!("Honeypot simulation: passed"),
+        None => println!("Honeypot simulation: not run"),
+    }
+    println!("Recommended trade amount: {}", assessment.recommended_trade_amount);
+}
+
+fn print_execution(side: &str, execution: &SwapExecution) {
+    if execution.canceled {
+        println!("{} was not included in time and got canceled. Transaction hash: {:?}", side, execution.tx_hash);
+        return;
+    }
+    match execution.tx_hash {
+        Some(tx_hash) => println!("{} sent. Transaction hash: {:?}", side, tx_hash),
+        None => println!("{} simulated, dry run is enabled.", side),
+    }
+    println!("Expected output: {}, gas fee: {} native", execution.quote.amount_out, format_ether(execution.gas_fee));
+}
@@ -0,0 +1,104 @@
+pub mod commands;
+
+use std::{env, path::PathBuf};
+
+use clap::{Parser, Subcommand};
+use ethers::types::{Address, U256};
+
+#[derive(Parser)]
+#[command(name = "sniper_bot", about = "Snipes new token pairs and manages the positions it opens")]
+pub struct Cli {
+    #[arg(long, short, global = true, value_name = "PATH", help = "Configuration file, defaults to the one of APP_ENV")]
+    pub config: Option<PathBuf>,
+
+    // Runs the bot when no command is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Listen to the configured exchanges and trade new pairs")]
+    Run,
+
+    #[command(about = "Assess the security of a token and print the score breakdown")]
+    Assess {
+        #[arg(help = "Chain id or network name")]
+        chain: String,
+        token: Address,
+        #[arg(long, help = "Exchange to simulate the round trip on, defaults to the first of the network")]
+        exchange: Option<String>,
+        #[arg(long, help = "Token the pair trades against, defaults to the base of the route found")]
+        base: Option<Address>,
+    },
+
+    #[command(about = "Buy a token with the native coin")]
+    Buy {
+        #[arg(help = "Chain id or network name")]
+        chain: String,
+        token: Address,
+        #[arg(help = "Amount to spend, in native units")]
+        amount: f64,
+        #[arg(long, help = "Exchange to trade on, defaults to the first of the network")]
+        exchange: Option<String>,
+        #[arg(long, default_value_t = 0.0, value_parser = parse_share, help = "Share of the bought tokens the token keeps, between 0 and 1")]
+        tax: f64,
+    },
+
+    #[command(about = "Sell a token for the native coin")]
+    Sell {
+        #[arg(help = "Chain id or network name")]
+        chain: String,
+        token: Address,
+        #[arg(help = "Amount to sell in the token's smallest unit, defaults to the whole balance", value_parser = parse_u256)]
+        amount: Option<U256>,
+        #[arg(long, help = "Exchange to trade on, defaults to the first of the network")]
+        exchange: Option<String>,
+        #[arg(long, default_value_t = 0.0, value_parser = parse_share, help = "Share of the sale proceeds the token keeps, between 0 and 1")]
+        tax: f64,
+    },
+
+    #[command(about = "List the open positions of the trade store")]
+    Positions {
+        #[arg(help = "Chain id or network name, all networks when omitted")]
+        chain: Option<String>,
+    },
+
+    #[command(about = "Print the wallet's native and token balances")]
+    Balances {
+        #[arg(help = "Chain id or network name")]
+        chain: String,
+        #[arg(help = "Tokens to check besides the network's base tokens")]
+        tokens: Vec<Address>,
+    },
+
+    #[command(name = "validate-config", about = "Load the configuration and report every problem found")]
+    ValidateConfig,
+}
+
+impl Cli {
+    pub fn config_path(&self) -> PathBuf {
+        self.config.clone().unwrap_or_else(default_config_path)
+    }
+}
+
+fn default_config_path() -> PathBuf {
+    let environment = env::var("APP_ENV").unwrap_or_else(|_| "development".to_string());
+    let config_file = match environment.as_str() {
+        "production" => "config_prod.json",
+        "local" => "config_local.json",
+        _ => "config_dev.json",  // Default to development
+    };
+    PathBuf::from(config_file)
+}
+
+fn parse_u256(value: &str) -> Result<U256, String> {
+    U256::from_dec_str(value).map_err(|e| format!("\"{}\" is not an amount: {}", value, e))
+}
+
+fn parse_share(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(share) if (0.0..=1.0).contains(&share) => Ok(share),
+        _ => Err(format!("\"{}\" is not a share between 0 and 1", value)),
+    }
+}
@@ -1,11 +1,167 @@
-use crate::models::config_models::Config;
-use std::fs;
+use crate::models::{config_models::Config, scoring_policy_models::ScoringPolicy};
+use serde_json::Value;
+use std::{env, fs};
 use std::path::Path;
 use std::error::Error;
 
+use super::validation::validate_config;
+
 // Implement the function to load and parse the configuration file
 pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config, Box<dyn Error>> {
     let config_str = fs::read_to_string(path)?;
-    let config: Config = serde_json::from_str(&config_str)?;
+    let mut config_value: Value = serde_json::from_str(&config_str)?;
+    interpolate_env(&mut config_value, "")?;
+    validate_config(&config_value)?;
+    resolve_scoring_policies(&mut config_value)?;
+    let config: Config = serde_json::from_value(config_value)?;
+    validate_slippage_policies(&config)?;
     Ok(config)
-}
\ No newline at end of file
+}
+
+// Replaces every ${VAR} in the string values with the environment variable, so secrets such as API keys can live in
+// .env and not in the committed JSON
+fn interpolate_env(value: &mut Value, path: &str) -> Result<(), String> {
+    match value {
+        Value::String(text) => *text = interpolate(text).map_err(|e| format!("{}: {}", path, e))?,
+        Value::Array(items) => {
+            for (index, item) in items.iter_mut().enumerate() {
+                interpolate_env(item, &format!("{}[{}]", path, index))?;
+            }
+        },
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                let field_path = if path.is_empty() { key.clone() } else { format!("{}.{}", path, key) };
+                interpolate_env(field, &field_path)?;
+            }
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+fn interpolate(text: &str) -> Result<String, String> {
+    let mut interpolated = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        interpolated.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or("unterminated ${ in value")? + start;
+        let name = &rest[start + 2..end];
+        let variable = env::var(name).map_err(|_| format!("environment variable {} is not set", name))?;
+        interpolated.push_str(&variable);
+        rest = &rest[end + 1..];
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+fn validate_slippage_policies(config: &Config) -> Result<(), Box<dyn Error>> {
+    for (index, network) in config.networks.iter().enumerate() {
+        let name = &network.metadata.name;
+        network.metadata.slippage_policy.validate()
+            .map_err(|e| format!("networks[{}] ({}): invalid slippagePolicy.{}", index, name, e))?;
+        for exchange in &network.exchanges {
+            if let Some(policy) = &exchange.slippage_policy {
+                policy.validate()
+                    .map_err(|e| format!("networks[{}] ({}): exchange {}: invalid slippagePolicy.{}", index, name, exchange.name, e))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Gives every network its full scoring policy: the built-in defaults, overridden by the top-level
+// "scoringPolicy" section, overridden by the network's own "scoringPolicy" in its metadata
+fn resolve_scoring_policies(config_value: &mut Value) -> Result<(), Box<dyn Error>> {
+    let mut global_policy = serde_json::to_value(ScoringPolicy::default())?;
+    if let Some(overrides) = config_value.get("scoringPolicy") {
+        merge(&mut global_policy, overrides);
+    }
+
+    let Some(networks) = config_value.get_mut("networks").and_then(Value::as_array_mut) else {
+        return Ok(());
+    };
+    for (index, network) in networks.iter_mut().enumerate() {
+        let Some(metadata) = network.get_mut("metadata").and_then(Value::as_object_mut) else {
+            continue;
+        };
+        let name = metadata.get("name").and_then(Value::as_str).unwrap_or("unnamed").to_string();
+
+        let mut policy = global_policy.clone();
+        if let Some(overrides) = metadata.get("scoringPolicy") {
+            merge(&mut policy, overrides);
+        }
+
+        let resolved: ScoringPolicy = serde_json::from_value(policy.clone())
+            .map_err(|e| format!("networks[{}] ({}): invalid scoringPolicy: {}", index, name, e))?;
+        resolved.validate()
+            .map_err(|e| format!("networks[{}] ({}): invalid scoringPolicy.{}", index, name, e))?;
+
+        metadata.insert("scoringPolicy".to_string(), policy);
+    }
+    Ok(())
+}
+
+// Objects are merged key by key, any other value replaces the base
+fn merge(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    },
+                }
+            }
+        },
+        (base, overrides) => *base = overrides.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn interpolate_replaces_set_variables() {
+        env::set_var("SNIPER_TEST_API_KEY", "secret");
+        assert_eq!(interpolate("https://rpc/${SNIPER_TEST_API_KEY}").unwrap(), "https://rpc/secret");
+        assert_eq!(interpolate("${SNIPER_TEST_API_KEY}-${SNIPER_TEST_API_KEY}").unwrap(), "secret-secret");
+        assert_eq!(interpolate("no placeholder, $ and } alone").unwrap(), "no placeholder, $ and } alone");
+    }
+
+    #[test]
+    fn interpolate_rejects_unset_variables() {
+        env::remove_var("SNIPER_TEST_UNSET");
+        assert_eq!(interpolate("${SNIPER_TEST_UNSET}"), Err("environment variable SNIPER_TEST_UNSET is not set".to_string()));
+    }
+
+    #[test]
+    fn interpolate_rejects_unterminated_placeholders() {
+        assert_eq!(interpolate("https://rpc/${API_KEY"), Err("unterminated ${ in value".to_string()));
+    }
+
+    #[test]
+    fn interpolate_env_names_the_field() {
+        env::remove_var("SNIPER_TEST_UNSET_FIELD");
+        let mut config = json!({ "networks": [{ "metadata": { "rpcUrl": "https://rpc/${SNIPER_TEST_UNSET_FIELD}" } }] });
+        assert_eq!(
+            interpolate_env(&mut config, ""),
+            Err("networks[0].metadata.rpcUrl: environment variable SNIPER_TEST_UNSET_FIELD is not set".to_string()),
+        );
+
+        env::set_var("SNIPER_TEST_SET_FIELD", "42");
+        let mut config = json!({ "chainId": "${SNIPER_TEST_SET_FIELD}", "count": 1 });
+        interpolate_env(&mut config, "").unwrap();
+        assert_eq!(config, json!({ "chainId": "42", "count": 1 }));
+    }
+
+    #[test]
+    fn merge_overrides_key_by_key() {
+        let mut base = json!({ "buyThreshold": 70.0, "risky": { "weight": 3.0, "checks": { "buyTax": { "scale": 100.0 } } } });
+        merge(&mut base, &json!({ "risky": { "checks": { "buyTax": { "penalty": 5.0 } } }, "buyThreshold": 80.0 }));
+        assert_eq!(base, json!({ "buyThreshold": 80.0, "risky": { "weight": 3.0, "checks": { "buyTax": { "scale": 100.0, "penalty": 5.0 } } } }));
+    }
+}
@@ -1,3 +1,5 @@
 pub mod loader;
+pub mod validation;
+pub mod watcher;
 
 pub use loader::load_config;
\ No newline at end of file
@@ -0,0 +1,256 @@
+use ethers::types::Address;
+use reqwest::Url;
+use serde_json::Value;
+
+const HTTP_SCHEMES: &[&str] = &["http", "https"];
+const WS_SCHEMES: &[&str] = &["ws", "wss"];
+
+// Checks the raw config before it is deserialized, so every problem is reported at once with its location.
+// URLs are never echoed back, they may carry an API key
+pub fn validate_config(config: &Value) -> Result<(), String> {
+    let Some(networks) = config.get("networks").and_then(Value::as_array) else {
+        return Err("networks: must be a list".into());
+    };
+
+    let mut errors = Vec::new();
+    for (index, network) in networks.iter().enumerate() {
+        let name = network.pointer("/metadata/name").and_then(Value::as_str).unwrap_or("unnamed");
+        let mut checker = Checker { prefix: format!("networks[{}] ({})", index, name), errors: &mut errors };
+        checker.network(network);
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Invalid configuration:\n{}", errors.join("\n")))
+    }
+}
+
+struct Checker<'a> {
+    prefix: String,
+    errors: &'a mut Vec<String>,
+}
+
+impl Checker<'_> {
+    fn network(&mut self, network: &Value) {
+        match network.get("metadata") {
+            Some(metadata) if metadata.is_object() => self.metadata(metadata),
+            _ => self.fail("metadata", "is missing"),
+        }
+
+        let Some(exchanges) = network.get("exchanges").and_then(Value::as_array) else {
+            self.fail("exchanges", "must be a list");
+            return;
+        };
+        for (index, exchange) in exchanges.iter().enumerate() {
+            let path = format!("exchanges[{}]", index);
+            self.string(exchange, &path, "name", true);
+            let base_implementation = self.string(exchange, &path, "baseImplementation", true);
+            self.address(exchange, &path, "factoryContractAddress", true);
+            self.address(exchange, &path, "routerContractAddress", true);
+            // Concentrated liquidity exchanges can only be quoted through their quoter
+            self.address(exchange, &path, "quoterContractAddress", base_implementation == Some("UniswapV3"));
+        }
+    }
+
+    fn metadata(&mut self, metadata: &Value) {
+        let path = "metadata";
+        self.string(metadata, path, "name", true);
+        self.string(metadata, path, "nativeCoinCoingeckoId", true);
+        self.chain_id(metadata, path);
+        self.url(metadata, path, "rpcUrl", HTTP_SCHEMES, true);
+        self.url(metadata, path, "wsUrl", WS_SCHEMES, true);
+        self.url(metadata, path, "simulationRpcUrl", HTTP_SCHEMES, false);
+        self.address(metadata, path, "walletAddress", true);
+        self.address(metadata, path, "wrappedNativeAddress", true);
+
+        if let Some(base_tokens) = metadata.get("baseTokens").and_then(Value::as_array) {
+            for (index, base_token) in base_tokens.iter().enumerate() {
+                self.address(base_token, &format!("{}.baseTokens[{}]", path, index), "address", true);
+            }
+        }
+        if let Some(signer) = metadata.get("signer") {
+            self.signer(signer, &format!("{}.signer", path));
+        }
+        if let Some(identity) = metadata.pointer("/privateSubmission/identity").filter(|identity| !identity.is_null()) {
+            self.signer(identity, &format!("{}.privateSubmission.identity", path));
+        }
+        if let Some(endpoints) = metadata.pointer("/privateSubmission/endpoints").and_then(Value::as_array) {
+            for (index, endpoint) in endpoints.iter().enumerate() {
+                let endpoint_path = format!("{}.privateSubmission.endpoints[{}]", path, index);
+                self.url(endpoint, &endpoint_path, "url", HTTP_SCHEMES, true);
+                self.url(endpoint, &endpoint_path, "simulationUrl", HTTP_SCHEMES, false);
+            }
+        }
+    }
+
+    fn signer(&mut self, signer: &Value, path: &str) {
+        match self.string(signer, path, "type", true) {
+            Some("env") => {
+                self.string(signer, path, "privateKeyEnv", false);
+            },
+            Some("keystore") => {
+                self.string(signer, path, "path", true);
+                self.string(signer, path, "passwordEnv", false);
+            },
+            Some(kind) => self.fail(&format!("{}.type", path), &format!("\"{}\" is not a signer type, expected env or keystore", kind)),
+            None => (),
+        }
+    }
+
+    // A number, or a string holding one
+    fn chain_id(&mut self, object: &Value, path: &str) {
+        if object.get("chainId").is_some_and(Value::is_u64) {
+            return;
+        }
+        let Some(chain_id) = self.string(object, path, "chainId", true) else {
+            return;
+        };
+        if chain_id.is_empty() || !chain_id.bytes().all(|byte| byte.is_ascii_digit()) || chain_id.parse::<u64>().is_err() {
+            self.fail(&format!("{}.chainId", path), &format!("\"{}\" is not a numeric chain id", chain_id));
+        }
+    }
+
+    fn address(&mut self, object: &Value, path: &str, key: &str, required: bool) {
+        let Some(address) = self.string(object, path, key, required) else {
+            return;
+        };
+        if !address.starts_with("0x") || address.parse::<Address>().is_err() {
+            self.fail(&format!("{}.{}", path, key), &format!("\"{}\" is not a valid address", address));
+        }
+    }
+
+    fn url(&mut self, object: &Value, path: &str, key: &str, schemes: &[&str], required: bool) {
+        let Some(url) = self.string(object, path, key, required) else {
+            return;
+        };
+        let field = format!("{}.{}", path, key);
+        match Url::parse(url) {
+            Ok(url) if schemes.contains(&url.scheme()) && url.host().is_some() => (),
+            Ok(url) => self.fail(&field, &format!("must be a {} URL with a host, got a {} URL", schemes.join(" or "), url.scheme())),
+            Err(e) => self.fail(&field, &format!("is not a valid URL: {}", e)),
+        }
+    }
+
+    // Value of a string field, None when it is absent or unusable, which is recorded unless the field is optional
+    fn string<'v>(&mut self, object: &'v Value, path: &str, key: &str, required: bool) -> Option<&'v str> {
+        match object.get(key) {
+            None | Some(Value::Null) => {
+                if required {
+                    self.fail(&format!("{}.{}", path, key), "is missing");
+                }
+                None
+            },
+            Some(Value::String(text)) => Some(text),
+            Some(_) => {
+                self.fail(&format!("{}.{}", path, key), "must be a string");
+                None
+            },
+        }
+    }
+
+    fn fail(&mut self, field: &str, problem: &str) {
+        self.errors.push(format!("{}: {} {}", self.prefix, field, problem));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const ADDRESS: &str = "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f";
+
+    fn config(metadata: Value, exchanges: Value) -> Value {
+        json!({ "networks": [{ "metadata": metadata, "exchanges": exchanges }] })
+    }
+
+    fn metadata() -> Value {
+        json!({
+            "name": "BNB Chain",
+            "nativeCoinCoingeckoId": "binancecoin",
+            "chainId": "56",
+            "rpcUrl": "https://bsc.publicnode.com",
+            "wsUrl": "wss://bsc.publicnode.com",
+            "walletAddress": ADDRESS,
+            "wrappedNativeAddress": ADDRESS,
+        })
+    }
+
+    fn exchange(base_implementation: &str) -> Value {
+        json!({
+            "name": "PancakeV2",
+            "baseImplementation": base_implementation,
+            "factoryContractAddress": ADDRESS,
+            "routerContractAddress": ADDRESS,
+        })
+    }
+
+    fn errors(config: &Value) -> Vec<String> {
+        match validate_config(config) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.lines().skip(1).map(str::to_string).collect(),
+        }
+    }
+
+    #[test]
+    fn valid_config_passes() {
+        assert_eq!(validate_config(&config(metadata(), json!([exchange("UniswapV2")]))), Ok(()));
+    }
+
+    #[test]
+    fn networks_must_be_a_list() {
+        assert_eq!(validate_config(&json!({})), Err("networks: must be a list".to_string()));
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let mut metadata = metadata();
+        metadata["walletAddress"] = json!("0x1234");
+        metadata["chainId"] = json!("0x38");
+        metadata.as_object_mut().unwrap().remove("nativeCoinCoingeckoId");
+        assert_eq!(errors(&config(metadata, json!([exchange("UniswapV2")]))), vec![
+            "networks[0] (BNB Chain): metadata.nativeCoinCoingeckoId is missing",
+            "networks[0] (BNB Chain): metadata.chainId \"0x38\" is not a numeric chain id",
+            "networks[0] (BNB Chain): metadata.walletAddress \"0x1234\" is not a valid address",
+        ]);
+    }
+
+    #[test]
+    fn numeric_chain_ids_are_accepted() {
+        let mut metadata = metadata();
+        metadata["chainId"] = json!(56);
+        assert_eq!(errors(&config(metadata, json!([]))), Vec::<String>::new());
+    }
+
+    #[test]
+    fn urls_are_checked_without_being_echoed() {
+        let mut metadata = metadata();
+        metadata["rpcUrl"] = json!("wss://rpc.example/secret-key");
+        metadata["wsUrl"] = json!("not a url");
+        let errors = errors(&config(metadata, json!([])));
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0], "networks[0] (BNB Chain): metadata.rpcUrl must be a http or https URL with a host, got a wss URL");
+        assert!(errors[1].starts_with("networks[0] (BNB Chain): metadata.wsUrl is not a valid URL"));
+        assert!(errors.iter().all(|error| !error.contains("secret-key")));
+    }
+
+    #[test]
+    fn uniswap_v3_exchanges_need_a_quoter() {
+        assert_eq!(errors(&config(metadata(), json!([exchange("UniswapV3")]))), vec![
+            "networks[0] (BNB Chain): exchanges[0].quoterContractAddress is missing",
+        ]);
+    }
+
+    #[test]
+    fn signer_types_are_checked() {
+        let mut metadata = metadata();
+        metadata["signer"] = json!({ "type": "ledger" });
+        metadata["privateSubmission"] = json!({ "identity": { "type": "keystore" } });
+        assert_eq!(errors(&config(metadata, json!([]))), vec![
+            "networks[0] (BNB Chain): metadata.signer.type \"ledger\" is not a signer type, expected env or keystore",
+            "networks[0] (BNB Chain): metadata.privateSubmission.identity.path is missing",
+        ]);
+    }
+}
@@ -0,0 +1,39 @@
+use std::{fs, path::Path, time::{Duration, SystemTime}};
+
+use tokio::time::interval;
+
+use crate::models::config_models::Config;
+
+use super::load_config;
+
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+// Polls the modification time of the config file and hands every new version that loads to `apply`,
+// an invalid edit is logged and the running config kept
+pub async fn watch_config<P: AsRef<Path>, F: FnMut(Config)>(path: P, mut apply: F) {
+    let path = path.as_ref();
+    let mut last_modified = modified(path);
+    let mut ticker = interval(CONFIG_POLL_INTERVAL);
+    ticker.tick().await; // The first tick is immediate, the running config was just loaded
+
+    loop {
+        ticker.tick().await;
+        let modified = modified(path);
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        match load_config(path) {
+            Ok(config) => {
+                log::info!("Configuration {} changed, applying it.", path.display());
+                apply(config);
+            },
+            Err(e) => log::error!("Configuration {} changed but cannot be loaded, keeping the running one: {}", path.display(), e),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
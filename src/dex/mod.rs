@@ -0,0 +1,154 @@
+pub mod uniswap_v2;
+pub mod uniswap_v3;
+
+use std::{sync::Arc, error::Error};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{Provider, Http},
+    types::{Address, Bytes, Log, H256, I256, U256},
+};
+
+use crate::models::config_models::ExchangeConfig;
+
+pub use uniswap_v2::UniswapV2Adapter;
+pub use uniswap_v3::UniswapV3Adapter;
+
+// A pair or pool announced by an exchange factory
+#[derive(Debug, Clone)]
+pub struct NewPool {
+    pub token_0: Address,
+    pub token_1: Address,
+    pub pool: Address,
+    pub fee: Option<u32>, // Fee tier, only announced by concentrated liquidity factories
+    pub tick_spacing: Option<i32>,
+}
+
+// An existing pool for a token, found without waiting for its creation event
+#[derive(Debug, Clone, Copy)]
+pub struct PoolMatch {
+    pub pool: Address,
+    pub fee: Option<u32>,
+    pub depth: U256, // Base token held by the pool
+}
+
+// A swap executed on a pool, amounts are signed from the pool's point of view (positive means paid into the pool)
+#[derive(Debug, Clone)]
+pub struct PoolSwap {
+    pub amount_0: I256,
+    pub amount_1: I256,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapDirection {
+    NativeForTokens,
+    TokensForNative,
+}
+
+#[derive(Debug, Clone)]
+pub struct SwapRequest {
+    pub direction: SwapDirection,
+    pub token_in: Address,
+    pub token_out: Address,
+    pub amount_in: U256,
+    pub amount_out_min: U256,
+    pub recipient: Address,
+    pub fee: Option<u32>, // Fee tier, only used by concentrated liquidity exchanges
+    pub fee_on_transfer: bool, // The token takes a cut of transfers, swaps must not rely on the computed amounts
+    pub via: Option<BaseHop>, // Base token between the native token and the token, None for pools paired with the wrapped native
+}
+
+// Intermediate token of a multi-hop swap and its pool against the wrapped native token
+#[derive(Debug, Clone, Copy)]
+pub struct BaseHop {
+    pub token: Address,
+    pub pool: Address,
+    pub fee: Option<u32>, // Fee tier of the pool, only used by concentrated liquidity exchanges
+}
+
+impl SwapRequest {
+    // Tokens the swap goes through in order, with the fee of the pool between each of them and the next
+    pub fn path(&self) -> (Vec<Address>, Vec<Option<u32>>) {
+        match (self.via, self.direction) {
+            (None, _) => (vec![self.token_in, self.token_out], vec![self.fee]),
+            (Some(via), SwapDirection::NativeForTokens) => (vec![self.token_in, via.token, self.token_out], vec![via.fee, self.fee]),
+            (Some(via), SwapDirection::TokensForNative) => (vec![self.token_in, via.token, self.token_out], vec![self.fee, via.fee]),
+        }
+    }
+}
+
+// Expected result of a swap, the extra details are only known to exchanges that simulate the swap to quote it
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub amount_out: U256, // Before slippage
+    pub gas_estimate: Option<U256>, // Gas used by the swap itself
+    pub ticks_crossed: Option<u32>, // Initialized ticks crossed, each one adds gas
+}
+
+// Router calldata ready to be wrapped in a transaction
+#[derive(Debug, Clone)]
+pub struct SwapCall {
+    pub to: Address,
+    pub data: Bytes,
+    pub value: Option<U256>,
+}
+
+// Everything that differs between exchange implementations lives behind this trait
+#[async_trait]
+pub trait DexAdapter: Send + Sync {
+    fn factory_address(&self) -> Address;
+
+    fn router_address(&self) -> Address;
+
+    // Pool discovery
+    fn pool_created_topic(&self) -> H256;
+
+    fn decode_pool_created(&self, log: Log) -> Result<NewPool, Box<dyn Error>>;
+
+    // Deepest pool trading `token` against `base_token`, None when the exchange has none
+    async fn find_pool(&self, provider: Arc<Provider<Http>>, token: Address, base_token: Address) -> Result<Option<PoolMatch>, Box<dyn Error>>;
+
+    async fn pool_tokens(&self, provider: Arc<Provider<Http>>, pool: Address) -> Result<(Address, Address), Box<dyn Error>>;
+
+    // Current price of `token` expressed in the other pool token, in raw units
+    async fn spot_price(&self, provider: Arc<Provider<Http>>, pool: Address, token: Address) -> Result<f64, Box<dyn Error>>;
+
+    // Quoting, without sending anything or needing a balance or an approval
+    async fn quote(&self, provider: Arc<Provider<Http>>, request: &SwapRequest) -> Result<Quote, Box<dyn Error>>;
+
+    // Whether swaps of tokens that take a cut of transfers can be built
+    fn supports_fee_on_transfer(&self) -> bool;
+
+    // Swap calldata
+    fn build_swap(&self, request: &SwapRequest) -> Result<SwapCall, Box<dyn Error>>;
+
+    // Output amount from the return data of the swap call
+    fn decode_swap_output(&self, request: &SwapRequest, output: &Bytes) -> Result<U256, Box<dyn Error>>;
+
+    // Typical gas used by a swap, for when it cannot be estimated
+    fn default_swap_gas(&self) -> U256;
+
+    // Events that add liquidity to a new pool, each one triggers a depth check
+    fn liquidity_topics(&self) -> Vec<H256>;
+
+    // Swap events
+    fn swap_topic(&self) -> H256;
+
+    fn decode_swap(&self, log: Log) -> Result<PoolSwap, Box<dyn Error>>;
+}
+
+type AdapterConstructor = fn(&ExchangeConfig) -> Result<Arc<dyn DexAdapter>, Box<dyn Error>>;
+
+// Every supported base implementation, adding a fork only takes a new entry here
+const ADAPTERS: &[(&str, AdapterConstructor)] = &[
+    ("UniswapV2", UniswapV2Adapter::from_config),
+    ("UniswapV3", UniswapV3Adapter::from_config),
+];
+
+pub fn adapter_for(exchange: &ExchangeConfig) -> Result<Arc<dyn DexAdapter>, Box<dyn Error>> {
+    ADAPTERS
+        .iter()
+        .find(|(base_implementation, _)| *base_implementation == exchange.base_implementation)
+        .map(|(_, constructor)| constructor(exchange))
+        .unwrap_or_else(|| Err(format!("Unknown base implementation {} for exchange {}", exchange.base_implementation, exchange.name).into()))
+}
@@ -0,0 +1,195 @@
+use std::{sync::Arc, error::Error};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    contract::{EthEvent, parse_log},
+    providers::{Provider, Http},
+    types::{Address, Bytes, Log, H256, I256, U256},
+};
+
+use crate::{
+    bindings::{
+        erc20::Erc20,
+        uniswap_v2_factory::{UniswapV2Factory, PairCreatedFilter},
+        uniswap_v2_pair::{UniswapV2Pair, MintFilter, SwapFilter, SyncFilter},
+        uniswap_v2_router02::{
+            UniswapV2Router02, SwapExactETHForTokensCall, SwapExactETHForTokensReturn, SwapExactTokensForETHCall, SwapExactTokensForETHReturn,
+            SwapExactETHForTokensSupportingFeeOnTransferTokensCall, SwapExactTokensForETHSupportingFeeOnTransferTokensCall,
+        },
+    },
+    models::config_models::ExchangeConfig,
+};
+
+use super::{DexAdapter, NewPool, PoolMatch, PoolSwap, Quote, SwapCall, SwapDirection, SwapRequest};
+
+const DEADLINE_SECS: i64 = 15 * 60; // 15 minutes
+const DEFAULT_SWAP_GAS: u64 = 200_000;
+
+pub struct UniswapV2Adapter {
+    factory: Address,
+    router: Address,
+}
+
+impl UniswapV2Adapter {
+    pub fn from_config(exchange: &ExchangeConfig) -> Result<Arc<dyn DexAdapter>, Box<dyn Error>> {
+        Ok(Arc::new(UniswapV2Adapter {
+            factory: exchange.factory_contract_address,
+            router: exchange.router_contract_address,
+        }))
+    }
+}
+
+#[async_trait]
+impl DexAdapter for UniswapV2Adapter {
+    fn factory_address(&self) -> Address {
+        self.factory
+    }
+
+    fn router_address(&self) -> Address {
+        self.router
+    }
+
+    fn pool_created_topic(&self) -> H256 {
+        PairCreatedFilter::signature()
+    }
+
+    fn decode_pool_created(&self, log: Log) -> Result<NewPool, Box<dyn Error>> {
+        let event: PairCreatedFilter = parse_log(log)?;
+        Ok(NewPool {
+            token_0: event.token_0,
+            token_1: event.token_1,
+            pool: event.pair,
+            fee: None,
+            tick_spacing: None,
+        })
+    }
+
+    async fn find_pool(&self, provider: Arc<Provider<Http>>, token: Address, base_token: Address) -> Result<Option<PoolMatch>, Box<dyn Error>> {
+        // A V2 factory holds a single pair per token couple
+        let pair = UniswapV2Factory::new(self.factory, provider.clone()).get_pair(token, base_token).call().await?;
+        if pair.is_zero() {
+            return Ok(None);
+        }
+        let depth = Erc20::new(base_token, provider).balance_of(pair).call().await?;
+        Ok(Some(PoolMatch { pool: pair, fee: None, depth }))
+    }
+
+    async fn pool_tokens(&self, provider: Arc<Provider<Http>>, pool: Address) -> Result<(Address, Address), Box<dyn Error>> {
+        let pair = UniswapV2Pair::new(pool, provider);
+        Ok((pair.token_0().call().await?, pair.token_1().call().await?))
+    }
+
+    async fn spot_price(&self, provider: Arc<Provider<Http>>, pool: Address, token: Address) -> Result<f64, Box<dyn Error>> {
+        let pair = UniswapV2Pair::new(pool, provider);
+        let token_0 = pair.token_0().call().await?;
+        let (reserve_0, reserve_1, _) = pair.get_reserves().call().await?;
+        if reserve_0 == 0 || reserve_1 == 0 {
+            return Err(format!("Pair {:?} has no reserves", pool).into());
+        }
+
+        let price = if token == token_0 {
+            reserve_1 as f64 / reserve_0 as f64
+        } else {
+            reserve_0 as f64 / reserve_1 as f64
+        };
+        Ok(price)
+    }
+
+    async fn quote(&self, provider: Arc<Provider<Http>>, request: &SwapRequest) -> Result<Quote, Box<dyn Error>> {
+        let router = UniswapV2Router02::new(self.router, provider);
+        let (path, _) = request.path();
+
+        // Fetch the expected output amount
+        let amounts_out: Vec<U256> = router.get_amounts_out(request.amount_in, path).call().await?;
+        let amount_out = amounts_out.last().ok_or("Failed to get output amount")?;
+        Ok(Quote { amount_out: *amount_out, gas_estimate: None, ticks_crossed: None })
+    }
+
+    fn supports_fee_on_transfer(&self) -> bool {
+        true
+    }
+
+    fn build_swap(&self, request: &SwapRequest) -> Result<SwapCall, Box<dyn Error>> {
+        let deadline = U256::from(Utc::now().timestamp() + DEADLINE_SECS);
+        let (path, _) = request.path();
+
+        // The supporting variants check the balance the recipient actually got against amountOutMin
+        let swap_call = match request.direction {
+            SwapDirection::NativeForTokens if request.fee_on_transfer => SwapCall {
+                to: self.router,
+                data: SwapExactETHForTokensSupportingFeeOnTransferTokensCall {
+                    amount_out_min: request.amount_out_min,
+                    path,
+                    to: request.recipient,
+                    deadline,
+                }.encode().into(),
+                value: Some(request.amount_in),
+            },
+            SwapDirection::TokensForNative if request.fee_on_transfer => SwapCall {
+                to: self.router,
+                data: SwapExactTokensForETHSupportingFeeOnTransferTokensCall {
+                    amount_in: request.amount_in,
+                    amount_out_min: request.amount_out_min,
+                    path,
+                    to: request.recipient,
+                    deadline,
+                }.encode().into(),
+                value: None,
+            },
+            SwapDirection::NativeForTokens => SwapCall {
+                to: self.router,
+                data: SwapExactETHForTokensCall {
+                    amount_out_min: request.amount_out_min,
+                    path,
+                    to: request.recipient,
+                    deadline,
+                }.encode().into(),
+                value: Some(request.amount_in), // This is the ETH amount you're sending
+            },
+            SwapDirection::TokensForNative => SwapCall {
+                to: self.router,
+                data: SwapExactTokensForETHCall {
+                    amount_in: request.amount_in,
+                    amount_out_min: request.amount_out_min,
+                    path,
+                    to: request.recipient,
+                    deadline,
+                }.encode().into(),
+                value: None, // No ETH sent along with token swap
+            },
+        };
+        Ok(swap_call)
+    }
+
+    fn decode_swap_output(&self, request: &SwapRequest, output: &Bytes) -> Result<U256, Box<dyn Error>> {
+        // Both swaps return the amounts of every hop, the last one is what the recipient gets
+        let amounts = match request.direction {
+            SwapDirection::NativeForTokens => SwapExactETHForTokensReturn::decode(output)?.amounts,
+            SwapDirection::TokensForNative => SwapExactTokensForETHReturn::decode(output)?.amounts,
+        };
+        Ok(*amounts.last().ok_or("Swap returned no amounts")?)
+    }
+
+    fn default_swap_gas(&self) -> U256 {
+        U256::from(DEFAULT_SWAP_GAS)
+    }
+
+    fn liquidity_topics(&self) -> Vec<H256> {
+        // Sync follows every reserve change, including a transfer to the pair that is synced later
+        vec![MintFilter::signature(), SyncFilter::signature()]
+    }
+
+    fn swap_topic(&self) -> H256 {
+        SwapFilter::signature()
+    }
+
+    fn decode_swap(&self, log: Log) -> Result<PoolSwap, Box<dyn Error>> {
+        let event: SwapFilter = parse_log(log)?;
+        Ok(PoolSwap {
+            amount_0: I256::from_raw(event.amount_0_in) - I256::from_raw(event.amount_0_out),
+            amount_1: I256::from_raw(event.amount_1_in) - I256::from_raw(event.amount_1_out),
+        })
+    }
+}
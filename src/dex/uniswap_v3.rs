@@ -0,0 +1,215 @@
+use std::{sync::Arc, error::Error};
+
+use async_trait::async_trait;
+use ethers::{
+    abi::{AbiDecode, AbiEncode},
+    contract::{EthEvent, parse_log},
+    providers::{Provider, Http},
+    types::{Address, Bytes, Log, H256, U256},
+};
+
+use crate::{
+    bindings::{
+        erc20::Erc20,
+        uniswap_v3_factory::{UniswapV3Factory, PoolCreatedFilter},
+        uniswap_v3_pool::{UniswapV3Pool, InitializeFilter, MintFilter, SwapFilter},
+        uniswap_v3_smart_router::{
+            ExactInputSingleCall, ExactInputSingleParams, ExactInputSingleReturn, ExactInputCall, ExactInputParams, ExactInputReturn,
+        },
+        uniswap_v3_quoter_v2::{UniswapV3QuoterV2, QuoteExactInputSingleParams},
+    },
+    models::config_models::ExchangeConfig,
+    utils::formatter::u256_to_f64,
+};
+
+use super::{DexAdapter, NewPool, PoolMatch, PoolSwap, Quote, SwapCall, SwapDirection, SwapRequest};
+
+const DEFAULT_FEE: u32 = 3000; // Default to a common fee tier, e.g., 0.3%
+const DEFAULT_SWAP_GAS: u64 = 300_000;
+const DEFAULT_FEE_TIERS: &[u32] = &[100, 500, 3000, 10000]; // 0.01%, 0.05%, 0.3% and 1%
+
+pub struct UniswapV3Adapter {
+    factory: Address,
+    router: Address,
+    quoter: Address,
+    fee_tiers: Vec<u32>, // Searched by pool discovery
+}
+
+impl UniswapV3Adapter {
+    pub fn from_config(exchange: &ExchangeConfig) -> Result<Arc<dyn DexAdapter>, Box<dyn Error>> {
+        Ok(Arc::new(UniswapV3Adapter {
+            factory: exchange.factory_contract_address,
+            router: exchange.router_contract_address,
+            quoter: exchange.quoter_contract_address
+                .ok_or_else(|| format!("Exchange {} has no quoterContractAddress", exchange.name))?,
+            fee_tiers: if exchange.fee_tiers.is_empty() { DEFAULT_FEE_TIERS.to_vec() } else { exchange.fee_tiers.clone() },
+        }))
+    }
+
+    fn exact_input_single_params(&self, request: &SwapRequest) -> ExactInputSingleParams {
+        ExactInputSingleParams {
+            token_in: request.token_in,
+            token_out: request.token_out,
+            fee: request.fee.unwrap_or(DEFAULT_FEE),
+            recipient: request.recipient,
+            amount_in: request.amount_in,
+            amount_out_minimum: request.amount_out_min,
+            sqrt_price_limit_x96: U256::zero(), // Zero to not set a specific price limit
+        }
+    }
+
+    fn exact_input_params(&self, request: &SwapRequest) -> ExactInputParams {
+        ExactInputParams {
+            path: encode_path(request),
+            recipient: request.recipient,
+            amount_in: request.amount_in,
+            amount_out_minimum: request.amount_out_min,
+        }
+    }
+}
+
+// Packed path of the multi-hop calls: every token followed by the 3-byte fee of the pool to the next one
+fn encode_path(request: &SwapRequest) -> Bytes {
+    let (tokens, fees) = request.path();
+    let mut path = Vec::with_capacity(tokens.len() * 23);
+    for (index, token) in tokens.iter().enumerate() {
+        path.extend_from_slice(token.as_bytes());
+        if let Some(fee) = fees.get(index) {
+            path.extend_from_slice(&fee.unwrap_or(DEFAULT_FEE).to_be_bytes()[1..]);
+        }
+    }
+    path.into()
+}
+
+#[async_trait]
+impl DexAdapter for UniswapV3Adapter {
+    fn factory_address(&self) -> Address {
+        self.factory
+    }
+
+    fn router_address(&self) -> Address {
+        self.router
+    }
+
+    fn pool_created_topic(&self) -> H256 {
+        PoolCreatedFilter::signature()
+    }
+
+    fn decode_pool_created(&self, log: Log) -> Result<NewPool, Box<dyn Error>> {
+        let event: PoolCreatedFilter = parse_log(log)?;
+        Ok(NewPool {
+            token_0: event.token_0,
+            token_1: event.token_1,
+            pool: event.pool,
+            fee: Some(event.fee),
+            tick_spacing: Some(event.tick_spacing),
+        })
+    }
+
+    async fn find_pool(&self, provider: Arc<Provider<Http>>, token: Address, base_token: Address) -> Result<Option<PoolMatch>, Box<dyn Error>> {
+        // Every fee tier can have its own pool for the same tokens, the deepest one gives the best fills
+        let factory = UniswapV3Factory::new(self.factory, provider.clone());
+        let base = Erc20::new(base_token, provider);
+        let mut deepest: Option<PoolMatch> = None;
+        for &fee in &self.fee_tiers {
+            let pool = factory.get_pool(token, base_token, fee).call().await?;
+            if pool.is_zero() {
+                continue;
+            }
+            let depth = base.balance_of(pool).call().await?;
+            if deepest.is_none_or(|deepest| depth > deepest.depth) {
+                deepest = Some(PoolMatch { pool, fee: Some(fee), depth });
+            }
+        }
+        Ok(deepest)
+    }
+
+    async fn pool_tokens(&self, provider: Arc<Provider<Http>>, pool: Address) -> Result<(Address, Address), Box<dyn Error>> {
+        let pool = UniswapV3Pool::new(pool, provider);
+        Ok((pool.token_0().call().await?, pool.token_1().call().await?))
+    }
+
+    async fn spot_price(&self, provider: Arc<Provider<Http>>, pool: Address, token: Address) -> Result<f64, Box<dyn Error>> {
+        let pool_contract = UniswapV3Pool::new(pool, provider);
+        let token_0 = pool_contract.token_0().call().await?;
+        let (sqrt_price_x96, ..) = pool_contract.slot_0().call().await?;
+        if sqrt_price_x96.is_zero() {
+            return Err(format!("Pool {:?} is not initialized", pool).into());
+        }
+
+        // slot0 holds sqrt(token1 / token0) as a Q64.96 fixed point number
+        let sqrt_price = u256_to_f64(sqrt_price_x96) / 2f64.powi(96);
+        let price_of_token_0 = sqrt_price * sqrt_price;
+        let price = if token == token_0 { price_of_token_0 } else { 1.0 / price_of_token_0 };
+        Ok(price)
+    }
+
+    async fn quote(&self, provider: Arc<Provider<Http>>, request: &SwapRequest) -> Result<Quote, Box<dyn Error>> {
+        // The quoter runs the swap and reverts with its result, the call needs neither a balance nor an approval
+        let quoter = UniswapV3QuoterV2::new(self.quoter, provider);
+        if request.via.is_some() {
+            let (amount_out, _, ticks_crossed, gas_estimate) = quoter.quote_exact_input(encode_path(request), request.amount_in).call().await?;
+            return Ok(Quote { amount_out, gas_estimate: Some(gas_estimate), ticks_crossed: Some(ticks_crossed.iter().sum()) });
+        }
+
+        let params = QuoteExactInputSingleParams {
+            token_in: request.token_in,
+            token_out: request.token_out,
+            amount_in: request.amount_in,
+            fee: request.fee.unwrap_or(DEFAULT_FEE),
+            sqrt_price_limit_x96: U256::zero(), // Zero to not set a specific price limit
+        };
+
+        let (amount_out, _, ticks_crossed, gas_estimate) = quoter.quote_exact_input_single(params).call().await?;
+        Ok(Quote { amount_out, gas_estimate: Some(gas_estimate), ticks_crossed: Some(ticks_crossed) })
+    }
+
+    // There is no fee-on-transfer variant, pools check the input they receive and selling a taxed token reverts
+    fn supports_fee_on_transfer(&self) -> bool {
+        false
+    }
+
+    fn build_swap(&self, request: &SwapRequest) -> Result<SwapCall, Box<dyn Error>> {
+        if request.fee_on_transfer {
+            return Err("UniswapV3 has no fee-on-transfer swap, taxed tokens cannot be traded".into());
+        }
+        let data = match request.via {
+            Some(_) => ExactInputCall { params: self.exact_input_params(request) }.encode().into(),
+            None => ExactInputSingleCall { params: self.exact_input_single_params(request) }.encode().into(),
+        };
+
+        let value = match request.direction {
+            SwapDirection::NativeForTokens => Some(request.amount_in), // The router wraps the ETH sent along
+            SwapDirection::TokensForNative => None,
+        };
+
+        Ok(SwapCall { to: self.router, data, value })
+    }
+
+    fn decode_swap_output(&self, request: &SwapRequest, output: &Bytes) -> Result<U256, Box<dyn Error>> {
+        match request.via {
+            Some(_) => Ok(ExactInputReturn::decode(output)?.amount_out),
+            None => Ok(ExactInputSingleReturn::decode(output)?.amount_out),
+        }
+    }
+
+    fn default_swap_gas(&self) -> U256 {
+        U256::from(DEFAULT_SWAP_GAS)
+    }
+
+    fn liquidity_topics(&self) -> Vec<H256> {
+        vec![InitializeFilter::signature(), MintFilter::signature()]
+    }
+
+    fn swap_topic(&self) -> H256 {
+        SwapFilter::signature()
+    }
+
+    fn decode_swap(&self, log: Log) -> Result<PoolSwap, Box<dyn Error>> {
+        let event: SwapFilter = parse_log(log)?;
+        Ok(PoolSwap {
+            amount_0: event.amount_0,
+            amount_1: event.amount_1,
+        })
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+use ethers::types::{Log, H256, U256, U64};
+
+const INITIAL_BACKOFF_SECS: u64 = 1;
+const MAX_BACKOFF_SECS: u64 = 60;
+
+// Delay before the next reconnection attempt, doubling on every failure up to MAX_BACKOFF_SECS
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let secs = INITIAL_BACKOFF_SECS.saturating_mul(2_u64.saturating_pow(attempt));
+    Duration::from_secs(secs.min(MAX_BACKOFF_SECS))
+}
+
+// Remembers the most recent logs by (tx hash, log index) so that events seen both in the
+// backfill and in the live stream are only processed once
+pub struct SeenLogs {
+    capacity: usize,
+    order: VecDeque<(H256, U256)>,
+    keys: HashSet<(H256, U256)>,
+}
+
+impl SeenLogs {
+    pub fn new(capacity: usize) -> Self {
+        SeenLogs {
+            capacity,
+            order: VecDeque::with_capacity(capacity),
+            keys: HashSet::with_capacity(capacity),
+        }
+    }
+
+    // Returns true if the log has not been seen before
+    pub fn insert(&mut self, log: &Log) -> bool {
+        let key = match (log.transaction_hash, log.log_index) {
+            (Some(tx_hash), Some(log_index)) => (tx_hash, log_index),
+            // Pending logs have no position yet, let them through
+            _ => return true,
+        };
+
+        if !self.keys.insert(key) {
+            return false;
+        }
+
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+// Tracks how far a log subscription got so it can resume from there after a reconnection
+pub struct StreamCursor {
+    pub last_block: Option<U64>, // Backfills start at this block, included, as it may have been covered only partly
+    seen_logs: SeenLogs,
+}
+
+impl StreamCursor {
+    pub fn new(capacity: usize) -> Self {
+        StreamCursor {
+            last_block: None,
+            seen_logs: SeenLogs::new(capacity),
+        }
+    }
+
+    // Returns true if the log is new and should be processed
+    pub fn record(&mut self, log: &Log) -> bool {
+        // Reorged out logs are re-sent with removed = true
+        if log.removed == Some(true) || !self.seen_logs.insert(log) {
+            return false;
+        }
+
+        if let Some(block_number) = log.block_number {
+            self.covered(block_number);
+        }
+        true
+    }
+
+    // Moves the cursor to a block the subscription is known to have reached, e.g. the chain head
+    pub fn covered(&mut self, block_number: U64) {
+        if self.last_block.is_none_or(|last| block_number > last) {
+            self.last_block = Some(block_number);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(tx: u64, index: u64, block: u64) -> Log {
+        Log {
+            transaction_hash: Some(H256::from_low_u64_be(tx)),
+            log_index: Some(U256::from(index)),
+            block_number: Some(U64::from(block)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff_delay(0), Duration::from_secs(1));
+        assert_eq!(backoff_delay(3), Duration::from_secs(8));
+        assert_eq!(backoff_delay(6), Duration::from_secs(MAX_BACKOFF_SECS));
+        assert_eq!(backoff_delay(u32::MAX), Duration::from_secs(MAX_BACKOFF_SECS));
+    }
+
+    #[test]
+    fn seen_logs_drop_duplicates() {
+        let mut seen = SeenLogs::new(4);
+        assert!(seen.insert(&log(1, 0, 10)));
+        assert!(!seen.insert(&log(1, 0, 10)));
+        // Same transaction, another log
+        assert!(seen.insert(&log(1, 1, 10)));
+    }
+
+    #[test]
+    fn seen_logs_forget_the_oldest_beyond_capacity() {
+        let mut seen = SeenLogs::new(2);
+        assert!(seen.insert(&log(1, 0, 10)));
+        assert!(seen.insert(&log(2, 0, 10)));
+        assert!(seen.insert(&log(3, 0, 10)));
+        // The first log was evicted, the two most recent are still known
+        assert!(seen.insert(&log(1, 0, 10)));
+        assert!(!seen.insert(&log(3, 0, 10)));
+    }
+
+    #[test]
+    fn pending_logs_are_always_new() {
+        let mut seen = SeenLogs::new(2);
+        let pending = Log::default();
+        assert!(seen.insert(&pending));
+        assert!(seen.insert(&pending));
+    }
+
+    #[test]
+    fn cursor_skips_removed_and_duplicate_logs() {
+        let mut cursor = StreamCursor::new(8);
+        assert!(cursor.record(&log(1, 0, 10)));
+        assert!(!cursor.record(&log(1, 0, 10)));
+        let removed = Log { removed: Some(true), ..log(2, 0, 11) };
+        assert!(!cursor.record(&removed));
+        assert_eq!(cursor.last_block, Some(U64::from(10)));
+    }
+
+    #[test]
+    fn cursor_only_moves_forward() {
+        let mut cursor = StreamCursor::new(8);
+        cursor.covered(U64::from(20));
+        assert!(cursor.record(&log(1, 0, 15))); // A late log is still processed
+        assert_eq!(cursor.last_block, Some(U64::from(20)));
+        cursor.covered(U64::from(25));
+        assert_eq!(cursor.last_block, Some(U64::from(25)));
+    }
+}
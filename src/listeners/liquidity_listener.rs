@@ -0,0 +1,75 @@
+use std::{error::Error, future::pending, time::Duration};
+
+use ethers::{
+    providers::{Provider, Ws, Middleware, StreamExt},
+    types::{Address, Filter, U256},
+};
+use tokio::time::{interval, sleep};
+
+use crate::{
+    models::config_models::{NetworkMetadata, ExchangeConfig},
+    dex::DexAdapter,
+    trading::router::{route_depth, TokenRoute},
+    utils::formatter::native_to_wei,
+};
+
+const LIQUIDITY_POLL_INTERVAL_SECS: u64 = 15; // Re-check the depth even when no event arrives
+
+// Waits until the route to `token` holds more than the network's minimum liquidity, valued in native units.
+// Returns the depth reached, or None when the timeout passed first
+pub async fn wait_for_liquidity(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    adapter: &dyn DexAdapter,
+    token: Address,
+    route: &TokenRoute,
+) -> Result<Option<U256>, Box<dyn Error>> {
+    let network_name = &network_metadata.name;
+    let exchange_name = &exchange.name;
+    let watch = &network_metadata.liquidity_watch;
+    let min_liquidity = native_to_wei(watch.min_native_liquidity)?;
+
+    // Liquidity events trigger an immediate check, the poll interval covers a dead connection
+    let ws_provider = Provider::<Ws>::connect(&network_metadata.ws_url).await.ok();
+    let filter = Filter::new().address(route.pool).topic0(adapter.liquidity_topics());
+    let mut events = match &ws_provider {
+        Some(ws_provider) => ws_provider.subscribe_logs(&filter).await.ok(),
+        None => None,
+    };
+    if events.is_none() {
+        log::warn!("[{} - {} - {}] Liquidity subscription unavailable, falling back to polling.", network_name, exchange_name, token);
+    }
+
+    log::info!(
+        "[{} - {} - {}] Waiting up to {}s for more than {} native liquidity on pool {}",
+        network_name, exchange_name, token, watch.timeout_secs, watch.min_native_liquidity, route.pool,
+    );
+    let timeout = sleep(Duration::from_secs(watch.timeout_secs));
+    tokio::pin!(timeout);
+    // The first tick fires at once, liquidity may have been added in the same block as the pool
+    let mut ticker = interval(Duration::from_secs(LIQUIDITY_POLL_INTERVAL_SECS));
+
+    loop {
+        tokio::select! {
+            _ = &mut timeout => return Ok(None),
+            _ = ticker.tick() => (),
+            event = async {
+                match events.as_mut() {
+                    Some(stream) => stream.next().await,
+                    None => pending().await,
+                }
+            } => {
+                if event.is_none() {
+                    log::warn!("[{} - {} - {}] Liquidity stream closed, falling back to polling.", network_name, exchange_name, token);
+                    events = None;
+                }
+            },
+        }
+
+        match route_depth(network_metadata, route).await {
+            Ok(depth) if depth > min_liquidity => return Ok(Some(depth)),
+            Ok(depth) => log::debug!("[{} - {} - {}] Pool {} holds {} native liquidity", network_name, exchange_name, token, route.pool, depth),
+            Err(e) => log::warn!("[{} - {} - {}] Failed to measure the liquidity of pool {}: {}", network_name, exchange_name, token, route.pool, e),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::collections::HashMap;
+
+use ethers::types::Address;
+use tokio::{spawn, sync::watch, task::JoinHandle};
+
+use crate::models::config_models::{Config, NetworkMetadata, ExchangeConfig};
+
+use super::new_tokens_listener::listen_to_new_tokens;
+
+// Config a listener runs with, replaced as a whole when the config file changes
+#[derive(Clone)]
+pub struct ListenerSettings {
+    pub network_metadata: NetworkMetadata,
+    pub exchange: ExchangeConfig,
+}
+
+// What a running listener is subscribed to, changing any of it takes a new listener
+#[derive(PartialEq, Eq)]
+struct Subscription {
+    ws_url: String,
+    base_implementation: String,
+    factory: Address,
+}
+
+impl Subscription {
+    fn of(settings: &ListenerSettings) -> Self {
+        Subscription {
+            ws_url: settings.network_metadata.ws_url.clone(),
+            base_implementation: settings.exchange.base_implementation.clone(),
+            factory: settings.exchange.factory_contract_address,
+        }
+    }
+}
+
+struct RunningListener {
+    subscription: Subscription,
+    settings: watch::Sender<ListenerSettings>, // Dropping it stops the listener
+    task: JoinHandle<()>,
+}
+
+// One new pool listener per chain id and exchange name, kept in line with the latest config
+#[derive(Default)]
+pub struct ListenerManager {
+    listeners: HashMap<(u64, String), RunningListener>,
+}
+
+impl ListenerManager {
+    // Starts the listeners of added exchanges, stops those of removed ones and hands the new settings to the others
+    pub fn apply(&mut self, config: &Config) {
+        let mut wanted = HashMap::new();
+        for network in &config.networks {
+            for exchange in &network.exchanges {
+                let settings = ListenerSettings { network_metadata: network.metadata.clone(), exchange: exchange.clone() };
+                if wanted.insert((network.metadata.chain_id, exchange.name.clone()), settings).is_some() {
+                    log::warn!("[{} - {}] Exchange is listed twice, the last entry is used.", network.metadata.name, exchange.name);
+                }
+            }
+        }
+
+        self.listeners.retain(|(chain_id, exchange_name), _| {
+            let kept = wanted.contains_key(&(*chain_id, exchange_name.clone()));
+            if !kept {
+                log::info!("[{} - {}] Exchange removed from the configuration, stopping its listener.", chain_id, exchange_name);
+            }
+            kept
+        });
+
+        for (key, settings) in wanted {
+            let subscription = Subscription::of(&settings);
+            match self.listeners.get(&key) {
+                // Policies are swapped in place, the next pool event is handled with them
+                Some(listener) if listener.subscription == subscription && !listener.task.is_finished() => {
+                    listener.settings.send_replace(settings);
+                },
+                Some(_) => {
+                    log::info!("[{} - {}] Subscription changed, restarting the listener.", settings.network_metadata.name, settings.exchange.name);
+                    self.listeners.insert(key, start_listener(subscription, settings));
+                },
+                None => {
+                    self.listeners.insert(key, start_listener(subscription, settings));
+                },
+            }
+        }
+    }
+}
+
+fn start_listener(subscription: Subscription, settings: ListenerSettings) -> RunningListener {
+    let (sender, receiver) = watch::channel(settings);
+    let task = spawn(listen_to_new_tokens(receiver));
+    RunningListener { subscription, settings: sender, task }
+}
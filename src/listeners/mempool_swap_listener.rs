@@ -1,8 +1,7 @@
 use ethers::{
     providers::{Provider, StreamExt, Middleware, Ws},
-    types::{TransactionRequest, U256, Address},
+    types::Address,
 };
-use std::sync::Arc;
 use std::error::Error;
 
 use crate::models::config_models::{NetworkMetadata, ExchangeConfig};
@@ -20,7 +19,7 @@ pub async fn listen_to_mempool_swaps(
     // Connect to an Ethereum provider that allows you to query the mempool
     let provider = Provider::<Ws>::connect(&network_metadata.ws_url).await.unwrap();
 
-    log::info!("[{} - {}] Listening to mempool for swaps involving Pair/Token: {}", network_name, exchange_name, pair_address);
+    log::info!("[{} - {}] Listening to mempool for swaps involving Pair {} of Token {}", network_name, exchange_name, pair_address, token_address);
 
     // Subscribe to the mempool (new pending transactions)
     let mut stream = provider.watch_pending_transactions().await?;
@@ -1,3 +1,6 @@
 pub mod new_tokens_listener;
 pub mod swaps_listener;
-pub mod mempool_swap_listener;
\ No newline at end of file
+pub mod mempool_swap_listener;
+pub mod liquidity_listener;
+pub mod event_supervisor;
+pub mod listener_manager;
\ No newline at end of file
@@ -1,108 +1,169 @@
 use std::{sync::Arc, error::Error};
 
-use ethers::{providers::{Provider, StreamExt, Ws}, abi::Address, types::BlockNumber};
-use tokio::sync::Barrier;
-// use futures::StreamExt; 
+use ethers::{
+    providers::{Provider, StreamExt, Ws, Middleware},
+    types::{Filter, Log},
+};
+use tokio::{sync::watch, time::sleep};
+// use futures::StreamExt;
 
 use crate::{
     models::config_models::{NetworkMetadata, ExchangeConfig},
-    bindings::{uniswap_v2_factory::{UniswapV2Factory, PairCreatedFilter}, uniswap_v3_factory::{UniswapV3Factory, PoolCreatedFilter}},
+    dex::{adapter_for, DexAdapter},
     analysis::processor::process_pair
 };
 
-pub async fn listen_to_new_tokens(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, barrier: Arc<Barrier>) {
-    let network_name = &network_metadata.name;
-    let exchange_name = &exchange.name;
+use super::{event_supervisor::{backoff_delay, StreamCursor}, listener_manager::ListenerSettings};
 
-    let provider = Provider::<Ws>::connect(&network_metadata.ws_url).await.unwrap();
-    let client = Arc::new(provider);
+const SEEN_LOGS_CAPACITY: usize = 4096;
 
-    log::trace!("[{} - {}] Task setup complete, waiting at barrier.", network_name, exchange_name);
-    barrier.wait().await;
-    log::info!("[{} - {}] Passed barrier, starting main workload.", network_name, exchange_name);
+// Runs until the listener manager drops the sender of `settings`. New settings apply to the next pool event,
+// pairs already being processed keep the settings they started with
+pub async fn listen_to_new_tokens(mut settings: watch::Receiver<ListenerSettings>) {
+    let ListenerSettings { network_metadata, exchange } = settings.borrow_and_update().clone();
+    let network_name = &network_metadata.name;
+    let exchange_name = &exchange.name;
+    log::info!("[{} - {}] Starting listener.", network_name, exchange_name);
 
-    match exchange.base_implementation.as_str() {
-        "UniswapV2" => {
-            match listen_to_uniswap_v2_new_pairs(client, network_metadata, exchange).await {
-                Ok(_) => (),
-                Err(e) => log::error!("[{} - {}] Error in listener: {}", network_name, exchange_name, e),
-            }
+    let adapter = match adapter_for(&exchange) {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            log::error!("[{} - {}] Cannot start listener: {}", network_name, exchange_name, e);
+            return;
         },
-        "UniswapV3" => {
-            match listen_to_uniswap_v3_new_pools(client, network_metadata, exchange).await {
-                Ok(_) => (),
-                Err(e) => log::error!("[{} - {}] Error in listener: {}", network_name, exchange_name, e),
-            }
+    };
+
+    let mut stop_signal = settings.clone();
+    tokio::select! {
+        _ = supervise_new_pools(&network_metadata, &exchange, &settings, adapter) => (),
+        _ = async { while stop_signal.changed().await.is_ok() {} } => {
+            log::info!("[{} - {}] Listener stopped.", network_name, exchange_name);
         },
-        _ => log::error!("Unknown Exchange: {}", exchange_name),
     }
 }
 
-async fn listen_to_uniswap_v2_new_pairs(client_clone: Arc<Provider<Ws>>, network_metadata: &NetworkMetadata, exchange: &ExchangeConfig) -> Result<(), Box<dyn Error>> {
+// Keeps the factory listener alive: reconnects with backoff whenever the connection or the
+// subscription drops, and backfills the events emitted while disconnected before streaming again
+async fn supervise_new_pools(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    settings: &watch::Receiver<ListenerSettings>,
+    adapter: Arc<dyn DexAdapter>,
+) {
+    let network_name = &network_metadata.name;
     let exchange_name = &exchange.name;
-    let contract_address: Address = exchange.factory_contract_address.parse().expect("Invalid contract address");
-    let contract = UniswapV2Factory::new(contract_address, client_clone);
-    let events = contract.event::<PairCreatedFilter>().from_block(BlockNumber::Latest);
-    let mut stream = events.stream().await.unwrap();
-    let network_name = network_metadata.name.clone();
-    
-    log::info!("[{} - {}] Listening for events PairCreatedFilter contract: {}", network_name, exchange_name, contract_address);
-
-    while let Some(event) = stream.next().await {
-        match event {
-            Ok(pair_created_event) => {
-                log::warn!(
-                    "[{} - {}] PairCreatedFilter event received: {:?}",
-                    network_name, exchange_name, pair_created_event,
-                );
-                process_pair(
-                    network_metadata,
-                    exchange,
-                    &pair_created_event.token_0,
-                    &pair_created_event.token_1,
-                    &pair_created_event.pair
-                ).await?;
-            },
-            Err(e) => {
-                log::error!("[{} - {}] Error listening for PairCreatedFilter events: {:?}", network_name, exchange_name, e);
+
+    let mut cursor = StreamCursor::new(SEEN_LOGS_CAPACITY);
+    let mut attempt: u32 = 0;
+
+    loop {
+        match Provider::<Ws>::connect(&network_metadata.ws_url).await {
+            Ok(provider) => {
+                let client = Arc::new(provider);
+                match listen_to_factory_events(client, network_metadata, exchange, settings, adapter.as_ref(), &mut cursor, &mut attempt).await {
+                    Ok(_) => log::warn!("[{} - {}] Factory stream closed, reconnecting.", network_name, exchange_name),
+                    Err(e) => log::error!("[{} - {}] Error in listener: {}", network_name, exchange_name, e),
+                }
             },
+            Err(e) => log::error!("[{} - {}] Failed to connect to {}: {}", network_name, exchange_name, network_metadata.ws_url, e),
         }
-    }
 
-    Ok(())
+        let delay = backoff_delay(attempt);
+        attempt = attempt.saturating_add(1);
+        log::info!("[{} - {}] Reconnecting in {}s (attempt {}).", network_name, exchange_name, delay.as_secs(), attempt);
+        sleep(delay).await;
+    }
 }
 
-async fn listen_to_uniswap_v3_new_pools(client_clone: Arc<Provider<Ws>>, network_metadata: &NetworkMetadata, exchange: &ExchangeConfig) -> Result<(), Box<dyn Error>> {
+async fn listen_to_factory_events(
+    client: Arc<Provider<Ws>>,
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    settings: &watch::Receiver<ListenerSettings>,
+    adapter: &dyn DexAdapter,
+    cursor: &mut StreamCursor,
+    attempt: &mut u32,
+) -> Result<(), Box<dyn Error>> {
+    let network_name = &network_metadata.name;
     let exchange_name = &exchange.name;
-    let contract_address: Address = exchange.factory_contract_address.parse().expect("Invalid contract address");
-    let contract = UniswapV3Factory::new(contract_address, client_clone);
-    
-    let events = contract.event::<PoolCreatedFilter>().from_block(BlockNumber::Latest);
-    let mut stream = events.stream().await.unwrap();
-    let network_name = network_metadata.name.clone();
-    
-    log::info!("[{} - {}] Listening for events PoolCreatedFilter contract: {}", network_name, exchange_name, contract_address);
-    
-    while let Some(event) = stream.next().await {
-        match event {
-            Ok(pair_created_event) => {
-                log::warn!(
-                    "[{} - {}] PoolCreatedFilter event received: {:?}",
-                    network_name, exchange_name, pair_created_event,
-                );
-                process_pair(
-                    network_metadata,
-                    exchange,
-                    &pair_created_event.token_0,
-                    &pair_created_event.token_1,
-                    &pair_created_event.pool
-                ).await?;
+    let contract_address = adapter.factory_address();
+    let filter = Filter::new().address(contract_address).topic0(adapter.pool_created_topic());
+
+    // Subscribe before backfilling so nothing falls between the two, duplicates are dropped by the cursor
+    let mut stream = client.subscribe_logs(&filter).await?;
+    let mut heads = client.subscribe_blocks().await?;
+    let to_block = client.get_block_number().await?;
+    *attempt = 0;
+
+    log::info!("[{} - {}] Listening for new pools on factory contract: {}", network_name, exchange_name, contract_address);
+
+    if let Some(from_block) = cursor.last_block {
+        let missed_logs = client.get_logs(&filter.clone().from_block(from_block).to_block(to_block)).await?;
+        log::info!(
+            "[{} - {}] Backfilling {} factory events from block {} to {}.",
+            network_name, exchange_name, missed_logs.len(), from_block, to_block,
+        );
+        for log in missed_logs {
+            let current = settings.borrow().clone();
+            handle_factory_log(&current.network_metadata, &current.exchange, adapter, log, cursor).await;
+        }
+    }
+
+    // Everything up to the head is covered now, by the backfill or by the subscription.
+    // New heads keep the cursor current through quiet stretches without any pool created
+    cursor.covered(to_block);
+
+    loop {
+        tokio::select! {
+            log = stream.next() => match log {
+                Some(log) => {
+                    let current = settings.borrow().clone();
+                    handle_factory_log(&current.network_metadata, &current.exchange, adapter, log, cursor).await;
+                },
+                None => return Ok(()),
             },
-            Err(e) => {
-                log::error!("[{} - {}] Error listening for PoolCreatedFilter events: {:?}", network_name, exchange_name, e);
+            head = heads.next() => match head {
+                Some(head) => if let Some(number) = head.number {
+                    cursor.covered(number);
+                },
+                None => return Ok(()),
             },
         }
     }
+}
+
+async fn handle_factory_log(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    adapter: &dyn DexAdapter,
+    log: Log,
+    cursor: &mut StreamCursor,
+) {
+    let network_name = &network_metadata.name;
+    let exchange_name = &exchange.name;
+
+    if !cursor.record(&log) {
+        return;
+    }
 
-    Ok(())
+    let new_pool = match adapter.decode_pool_created(log) {
+        Ok(new_pool) => new_pool,
+        Err(e) => {
+            log::error!("[{} - {}] Error decoding new pool event: {:?}", network_name, exchange_name, e);
+            return;
+        },
+    };
+
+    log::warn!(
+        "[{} - {}] New pool event received: {:?}",
+        network_name, exchange_name, new_pool,
+    );
+    // Processing waits for liquidity and the assessment, the factory stream must keep going meanwhile
+    let network_metadata = network_metadata.clone();
+    let exchange = exchange.clone();
+    tokio::spawn(async move {
+        if let Err(e) = process_pair(&network_metadata, &exchange, &new_pool).await {
+            log::error!("[{} - {}] Error processing pair {}: {}", network_metadata.name, exchange.name, new_pool.pool, e);
+        }
+    });
 }
@@ -1,14 +1,12 @@
 use ethers::{
-    providers::{Provider, StreamExt, Ws},
-    types::{Address, BlockNumber, U256, I256},
-    contract::Contract,
-    core::types::Filter,
+    providers::{Provider, StreamExt, Ws, Http, Middleware},
+    types::{Address, Filter, I256},
 };
 use std::sync::Arc;
 use std::error::Error;
 
 use crate::{
-    bindings::{uniswap_v2_pair::{UniswapV2Pair, SwapFilter}, uniswap_v3_pool::{UniswapV3Pool, SwapFilter as SwapFilterV3}},
+    dex::{adapter_for, DexAdapter},
     models::config_models::{NetworkMetadata, ExchangeConfig}
 };
 
@@ -27,105 +25,52 @@ pub async fn listen_to_swaps(
     let provider = Provider::<Ws>::connect(&network_metadata.ws_url).await.unwrap();
     let client = Arc::new(provider);
 
-    match exchange.base_implementation.as_str() {
-        "UniswapV2" => {
-            match listen_to_swaps_v2(client, network_metadata, exchange, token_address, pair_address).await {
-                Ok(_) => (),
-                Err(e) => log::error!("[{} - {}] Error in listener: {}", network_name, exchange_name, e),
-            }
+    let adapter = match adapter_for(exchange) {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            log::error!("[{} - {}] Cannot start listener: {}", network_name, exchange_name, e);
+            return;
         },
-        "UniswapV3" => {
-            match listen_to_swaps_v3(client, network_metadata, exchange, token_address, pair_address).await {
-                Ok(_) => (),
-                Err(e) => log::error!("[{} - {}] Error in listener: {}", network_name, exchange_name, e),
-            }
-        },
-        _ => log::error!("Unknown Exchange: {}", exchange_name),
+    };
+    match listen_to_pool_swaps(client, adapter.as_ref(), network_metadata, exchange, token_address, pair_address).await {
+        Ok(_) => (),
+        Err(e) => log::error!("[{} - {}] Error in listener: {}", network_name, exchange_name, e),
     }
 }
 
-async fn listen_to_swaps_v2(
+async fn listen_to_pool_swaps(
     client_clone: Arc<Provider<Ws>>,
+    adapter: &dyn DexAdapter,
     network_metadata: &NetworkMetadata,
     exchange: &ExchangeConfig,
     token_address: Address,
     pair_address: Address
 ) -> Result<(), Box<dyn Error>> {
     let exchange_name = &exchange.name;
-    let pair_address_clone = pair_address.clone();
-    let pair_contract = UniswapV2Pair::new(pair_address_clone, client_clone);
-    let events = pair_contract.event::<SwapFilter>().from_block(BlockNumber::Latest);
-    let mut stream = events.stream().await.unwrap();
     let network_name = network_metadata.name.clone();
-    
-    log::info!("[{} - {}] Listening for events PairCreatedFilter contract: {}", network_name, exchange_name, pair_address);
+    let http_provider = Arc::new(Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?);
 
-    while let Some(swap) = stream.next().await {
-        match swap {
-            Ok(swap_event) => {
-                // Determine if the token of interest is token0 or token1
-                let is_token0 = pair_contract.token_0().await? == token_address;
-    
-                let amount_in = if is_token0 { swap_event.amount_0_in } else { swap_event.amount_1_in };
-                let amount_out = if is_token0 { swap_event.amount_0_out } else { swap_event.amount_1_out };
-    
-                // A 'sell' of the token of interest occurs when there's an amount 'in' and no amount 'out'
-                if amount_in > U256::from(0) && amount_out == U256::from(0) {
-                    // This is a sell for the token of interest
-                    log::info!("[{} - {}] Sell detected: {:?}", network_name, exchange_name, swap_event);
-                    // Handle the sell event here (logging, notifications, further processing...)
-                }
-            },
-            Err(e) => {
-                log::error!("[{} - {}] Error listening for Swap events: {:?}", network_name, exchange_name, e);
-            },
-        }
+    // Retrieve token0 and token1 addresses from the pair
+    let (token0, token1) = adapter.pool_tokens(http_provider, pair_address).await?;
+    if token0 != token_address && token1 != token_address {
+        return Err(format!("Token {:?} is not part of pair {:?}", token_address, pair_address).into());
     }
 
-    Ok(())
-}
-
-
-async fn listen_to_swaps_v3(
-    client_clone: Arc<Provider<Ws>>,
-    network_metadata: &NetworkMetadata,
-    exchange: &ExchangeConfig,
-    token_address: Address,
-    pair_address: Address
-) -> Result<(), Box<dyn Error>> {
-    let exchange_name = &exchange.name;
-    let pair_contract = UniswapV3Pool::new(pair_address.clone(), client_clone.clone());
-    let events = pair_contract.event::<SwapFilterV3>().from_block(BlockNumber::Latest);
-    let mut stream = events.stream().await.unwrap();
-    let network_name = network_metadata.name.clone();
-    let wrapped_native_token = network_metadata.wrapped_native_address;
-
-    // Retrieve token0 and token1 addresses from the pair
-    let token0 = pair_contract.token_0().await?;
-    let token1 = pair_contract.token_1().await?;
+    let filter = Filter::new().address(pair_address).topic0(adapter.swap_topic());
+    let mut stream = client_clone.subscribe_logs(&filter).await?;
 
     log::info!("[{} - {}] Listening for Swap events on contract: {}", network_name, exchange_name, pair_address);
 
-    while let Some(swap) = stream.next().await {
-        match swap {
+    while let Some(log) = stream.next().await {
+        match adapter.decode_swap(log) {
             Ok(swap_event) => {
-                // Determine if the target token is token0 or token1
-                let is_target_token0 = token0 == token_address && token0 != wrapped_native_token;
-                let is_target_token1 = token1 == token_address && token1 != wrapped_native_token;
-
                 // Determine the amount for the target token
-                let amount_target_token = if is_target_token0 {
-                    swap_event.amount_0
-                } else if is_target_token1 {
-                    swap_event.amount_1
-                } else {
-                    continue; // Neither token in the pair is the target token
-                };
+                let amount_target_token = if token0 == token_address { swap_event.amount_0 } else { swap_event.amount_1 };
 
-                // Check if it's a sell event of the target token
+                // The target token being paid into the pool means someone sold it
                 if amount_target_token > I256::from(0) {
                     log::info!("[{} - {}] Sell of target token detected: {:?}", network_name, exchange_name, swap_event);
-                    // Handle the sell event here
+                    // Handle the sell event here (logging, notifications, further processing...)
                 }
             },
             Err(e) => {
@@ -7,28 +7,22 @@ mod analysis;
 mod listeners;
 mod utils;
 mod trading;
+mod dex;
+mod storage;
+mod cli;
 
-use ethers::{
-    providers::{Provider, Ws},
-};
-
-use tokio::spawn;
-use tokio::sync::Barrier;
-use std::{sync::Arc};
-use std::error::Error; 
-use futures::future::join_all;
+use std::process::ExitCode;
+use clap::Parser;
 use log::LevelFilter;
 use env_logger::Builder;
 
 use dotenv::dotenv;
-use std::env;
 
-use crate::{config::load_config, listeners::new_tokens_listener::listen_to_new_tokens};
-use std::path::Path;
+use crate::cli::{commands, Cli, Command};
 
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
+async fn main() -> ExitCode {
     // Initialize the dotenv
     dotenv().ok();
     
@@ -37,42 +31,24 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .filter(Some("tokio"), LevelFilter::Warn)
         .init();
 
-    let environment = env::var("APP_ENV").unwrap_or_else(|_| "development".to_string());
-    let config_file = match environment.as_str() {
-        "production" => "config_prod.json",
-        "local" => "config_local.json",
-        _ => "config_dev.json",  // Default to development
+    let cli = Cli::parse();
+    let config_path = cli.config_path();
+    let result = match cli.command.unwrap_or(Command::Run) {
+        Command::Run => commands::run(&config_path).await,
+        Command::Assess { chain, token, exchange, base } => commands::assess(&config_path, &chain, token, exchange.as_deref(), base).await,
+        Command::Buy { chain, token, amount, exchange, tax } => commands::buy(&config_path, &chain, token, amount, exchange.as_deref(), tax).await,
+        Command::Sell { chain, token, amount, exchange, tax } => commands::sell(&config_path, &chain, token, amount, exchange.as_deref(), tax).await,
+        Command::Positions { chain } => commands::positions(chain.as_deref()),
+        Command::Balances { chain, tokens } => commands::balances(&config_path, &chain, &tokens).await,
+        Command::ValidateConfig => commands::validate_config(&config_path),
     };
 
-    let config = load_config(Path::new(config_file))?;
-    println!("Loaded configuration for the {} environment.", environment);
-
-    let total_exchanges = config.networks.iter().map(|network| network.exchanges.len()).sum::<usize>();
-    let barrier = Arc::new(Barrier::new(total_exchanges + 1)); // +1 for the main thread
-
-    let mut tasks = Vec::new();
-
-    for network in config.networks.into_iter() {        
-        for exchange in network.exchanges.into_iter() {
-            // let provider = Provider::<Ws>::connect(&network.metadata.ws_url).await?;
-            // let client = Arc::new(provider);
-            let barrier_clone = barrier.clone();
-            let network_metadata = network.metadata.clone();
-
-            // Spawn each listener as a separate asynchronous task
-            let task: tokio::task::JoinHandle<()> = spawn(async move {
-                listen_to_new_tokens(&network_metadata, &exchange, barrier_clone).await;
-            });
-            tasks.push(task);
-        }
+    // Errors are printed for the operator, not debug-formatted
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        },
     }
-
-    // Wait for all tasks to reach the barrier point
-    barrier.wait().await;
-
-    // Wait for all tasks to complete
-    join_all(tasks).await;
-
-    log::info!("All tasks have completed.");
-    Ok(())
 }
@@ -1,5 +1,7 @@
-use serde::Deserialize;
-use ethers::types::H160;
+use serde::{de, Deserialize, Deserializer};
+use ethers::types::{Address, H160};
+
+use super::scoring_policy_models::ScoringPolicy;
 
 #[derive(Deserialize)]
 pub struct Config {
@@ -16,8 +18,8 @@ pub struct NetworkConfig {
 pub struct NetworkMetadata {
     pub name: String,
     pub symbol: String,
-    #[serde(rename = "chainId")]
-    pub chain_id: String,
+    #[serde(rename = "chainId", deserialize_with = "deserialize_chain_id")]
+    pub chain_id: u64,
     #[serde(rename = "nativeCoinCoingeckoId")]
     pub native_coin_coingecko_id: String,
     #[serde(rename = "rpcUrl")]
@@ -25,9 +27,321 @@ pub struct NetworkMetadata {
     #[serde(rename = "wsUrl")]
     pub ws_url: String,
     #[serde(rename = "walletAddress")]
-    pub wallet_address: H160,
+    pub wallet_address: H160, // Checked against the signer at startup
+    #[serde(default)]
+    pub signer: SignerConfig, // Key the network's transactions are signed with
     #[serde(rename = "wrappedNativeAddress")]
     pub wrapped_native_address: H160,
+    #[serde(rename = "baseTokens", default)]
+    pub base_tokens: Vec<BaseToken>, // Other tokens new pairs may be launched against, traded through their pool with the wrapped native
+    #[serde(rename = "simulationRpcUrl", default)]
+    pub simulation_rpc_url: Option<String>, // Endpoint for the honeypot simulation, e.g. a local fork, defaults to rpcUrl
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool, // Quote and record trades without sending any transaction
+    #[serde(rename = "liquidityWatch", default)]
+    pub liquidity_watch: LiquidityWatch,
+    #[serde(rename = "exitRules", default)]
+    pub exit_rules: ExitRules,
+    #[serde(rename = "gasPolicy", default)]
+    pub gas_policy: GasPolicy,
+    #[serde(rename = "transactionPolicy", default)]
+    pub transaction_policy: TransactionPolicy,
+    #[serde(rename = "slippagePolicy", default)]
+    pub slippage_policy: SlippagePolicy,
+    #[serde(rename = "approvalPolicy", default)]
+    pub approval_policy: ApprovalPolicy, // How much of a token the router is allowed to spend before a sell
+    #[serde(rename = "privateSubmission", default)]
+    pub private_submission: PrivateSubmission, // Buys and sells skip the public mempool when endpoints are listed
+    #[serde(rename = "scoringPolicy", default)]
+    pub scoring_policy: ScoringPolicy, // Resolved by the loader from the defaults, the global section and the network overrides
+}
+
+// Older configs write the chain id as a string, plain numbers are accepted too
+fn deserialize_chain_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ChainId {
+        Number(u64),
+        Text(String),
+    }
+
+    match ChainId::deserialize(deserializer)? {
+        ChainId::Number(chain_id) => Ok(chain_id),
+        ChainId::Text(text) => text.parse().map_err(|_| de::Error::custom(format!("\"{}\" is not a numeric chain id", text))),
+    }
+}
+
+// Where a signing key comes from
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(tag = "type")]
+pub enum SignerConfig {
+    #[serde(rename = "env")]
+    Env {
+        #[serde(rename = "privateKeyEnv", default = "default_private_key_env")]
+        private_key_env: String, // Variable holding the hex private key
+    },
+    #[serde(rename = "keystore")]
+    Keystore {
+        path: String, // Encrypted JSON keystore
+        #[serde(rename = "passwordEnv", default = "default_keystore_password_env")]
+        password_env: String, // The password is asked for on the terminal when the variable is not set
+    },
+}
+
+impl Default for SignerConfig {
+    fn default() -> Self {
+        SignerConfig::Env { private_key_env: default_private_key_env() }
+    }
+}
+
+fn default_private_key_env() -> String {
+    "PRIVATE_KEY".to_string()
+}
+
+fn default_keystore_password_env() -> String {
+    "KEYSTORE_PASSWORD".to_string()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BaseToken {
+    pub symbol: String,
+    pub address: H160,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct LiquidityWatch {
+    #[serde(rename = "minNativeLiquidity", default)]
+    pub min_native_liquidity: f64, // New pools are assessed once they hold more than this, valued in native units
+    #[serde(rename = "timeoutSecs", default = "default_liquidity_timeout_secs")]
+    pub timeout_secs: u64, // The trade is canceled when the liquidity does not arrive in time
+}
+
+impl Default for LiquidityWatch {
+    fn default() -> Self {
+        LiquidityWatch {
+            min_native_liquidity: 0.0,
+            timeout_secs: default_liquidity_timeout_secs(),
+        }
+    }
+}
+
+fn default_liquidity_timeout_secs() -> u64 {
+    10 * 60
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ExitRules {
+    #[serde(rename = "takeProfitMultiplier")]
+    pub take_profit_multiplier: f64, // Sell once the position is worth this many times what was paid
+    #[serde(rename = "stopLossPercentage")]
+    pub stop_loss_percentage: f64, // Sell once the position lost this percentage of its value
+    #[serde(rename = "trailingStopPercentage", default)]
+    pub trailing_stop_percentage: Option<f64>, // Sell once the position fell this percentage from its peak
+    #[serde(rename = "maxHoldSeconds")]
+    pub max_hold_seconds: u64, // Sell whatever the price after holding this long
+    #[serde(rename = "maxCloseAttempts", default = "default_max_close_attempts")]
+    pub max_close_attempts: u32, // Sells tried before the position is left to be closed by hand
+}
+
+impl Default for ExitRules {
+    fn default() -> Self {
+        ExitRules {
+            take_profit_multiplier: 2.0,
+            stop_loss_percentage: 30.0,
+            trailing_stop_percentage: None,
+            max_hold_seconds: 60 * 60,
+            max_close_attempts: default_max_close_attempts(),
+        }
+    }
+}
+
+fn default_max_close_attempts() -> u32 {
+    5
+}
+
+#[derive(Deserialize, Clone)]
+pub struct GasPolicy {
+    #[serde(default = "default_eip1559")]
+    pub eip1559: bool, // Send type-2 transactions, legacy pricing is still used when the chain reports no base fee
+    #[serde(rename = "feeHistoryBlocks", default = "default_fee_history_blocks")]
+    pub fee_history_blocks: u64, // Number of recent blocks sampled for priority fees
+    #[serde(default = "UrgencyPolicy::snipe")]
+    pub snipe: UrgencyPolicy, // Entering a position, racing other buyers
+    #[serde(default = "UrgencyPolicy::exit")]
+    pub exit: UrgencyPolicy, // Leaving a position
+    #[serde(rename = "maxFeePerTrade", default)]
+    pub max_fee_per_trade: Option<f64>, // Most a single transaction may cost in gas, in native units
+}
+
+#[derive(Deserialize, Clone)]
+pub struct UrgencyPolicy {
+    #[serde(rename = "priorityFeePercentile")]
+    pub priority_fee_percentile: f64, // Percentile of the recent priority fees to tip, between 0 and 100
+    #[serde(rename = "minPriorityFeeGwei", default)]
+    pub min_priority_fee_gwei: f64,
+    #[serde(rename = "baseFeeMultiplier")]
+    pub base_fee_multiplier: f64, // Headroom over the next base fee, 2.0 survives about six full blocks
+    #[serde(rename = "legacyGasPriceMultiplier", default = "default_legacy_gas_price_multiplier")]
+    pub legacy_gas_price_multiplier: f64, // Applied to eth_gasPrice on chains without EIP-1559
+}
+
+fn default_eip1559() -> bool {
+    true
+}
+
+fn default_fee_history_blocks() -> u64 {
+    10
+}
+
+fn default_legacy_gas_price_multiplier() -> f64 {
+    1.0
+}
+
+impl UrgencyPolicy {
+    fn snipe() -> Self {
+        UrgencyPolicy { priority_fee_percentile: 90.0, min_priority_fee_gwei: 1.0, base_fee_multiplier: 2.0, legacy_gas_price_multiplier: 1.2 }
+    }
+
+    fn exit() -> Self {
+        UrgencyPolicy { priority_fee_percentile: 50.0, min_priority_fee_gwei: 0.0, base_fee_multiplier: 1.5, legacy_gas_price_multiplier: 1.0 }
+    }
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        GasPolicy {
+            eip1559: default_eip1559(),
+            fee_history_blocks: default_fee_history_blocks(),
+            snipe: UrgencyPolicy::snipe(),
+            exit: UrgencyPolicy::exit(),
+            max_fee_per_trade: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct TransactionPolicy {
+    #[serde(rename = "inclusionTimeoutSecs")]
+    pub inclusion_timeout_secs: u64, // How long a transaction may stay pending before it is replaced
+    #[serde(rename = "feeBumpPercentage")]
+    pub fee_bump_percentage: f64, // Fee increase of each replacement, nodes reject less than 10%
+    #[serde(rename = "maxReplacements")]
+    pub max_replacements: u32, // Fee bumps before canceling the transaction, or only waiting for it when cancelWhenExhausted is off
+    #[serde(rename = "cancelWhenExhausted", default)]
+    pub cancel_when_exhausted: bool, // Free the nonce with a zero-value self-transfer once the bumps are used up
+}
+
+impl Default for TransactionPolicy {
+    fn default() -> Self {
+        TransactionPolicy {
+            inclusion_timeout_secs: 60,
+            fee_bump_percentage: 12.5,
+            max_replacements: 3,
+            cancel_when_exhausted: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SlippagePolicy {
+    #[serde(rename = "baseBps")]
+    pub base_bps: u32, // Tolerance of every trade, in basis points of the quoted output
+    #[serde(rename = "maxBps")]
+    pub max_bps: u32, // Ceiling after the dynamic terms are added
+    #[serde(rename = "depthMultiplier", default)]
+    pub depth_multiplier: f64, // Basis points added per basis point of the pool's native reserve the trade moves
+    #[serde(rename = "taxMultiplier", default)]
+    pub tax_multiplier: f64, // Basis points added per basis point of transfer tax, taxes can change between blocks
+}
+
+impl Default for SlippagePolicy {
+    fn default() -> Self {
+        SlippagePolicy {
+            base_bps: 2_000,
+            max_bps: 5_000,
+            depth_multiplier: 0.0,
+            tax_multiplier: 0.0,
+        }
+    }
+}
+
+impl SlippagePolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.base_bps > self.max_bps {
+            return Err(format!("baseBps: {} is above maxBps {}", self.base_bps, self.max_bps));
+        }
+        if self.max_bps > 10_000 {
+            return Err(format!("maxBps: {} is above 10000", self.max_bps));
+        }
+        for (field, value) in [("depthMultiplier", self.depth_multiplier), ("taxMultiplier", self.tax_multiplier)] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("{}: {} must be a non-negative number", field, value));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ApprovalPolicy {
+    #[default]
+    #[serde(rename = "exact")]
+    Exact, // Approve what the sell spends, nothing is left for the router afterwards
+    #[serde(rename = "max")]
+    Max, // Approve the maximum once per token and router
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PrivateSubmission {
+    #[serde(default)]
+    pub endpoints: Vec<PrivateEndpoint>,
+    #[serde(rename = "fanOut", default)]
+    pub fan_out: FanOut,
+    #[serde(rename = "targetBlocks", default = "default_target_blocks")]
+    pub target_blocks: u64, // Blocks the transaction may be included in after each submission
+    #[serde(default)]
+    pub identity: Option<SignerConfig>, // Key bundle relays know the searcher by, the network's signer when unset
+}
+
+impl Default for PrivateSubmission {
+    fn default() -> Self {
+        PrivateSubmission {
+            endpoints: Vec::new(),
+            fan_out: FanOut::default(),
+            target_blocks: default_target_blocks(),
+            identity: None,
+        }
+    }
+}
+
+fn default_target_blocks() -> u64 {
+    3
+}
+
+#[derive(Deserialize, Clone)]
+pub struct PrivateEndpoint {
+    pub name: String,
+    pub kind: EndpointKind,
+    pub url: String, // Point it to a mock relay for tests
+    #[serde(rename = "simulationUrl", default)]
+    pub simulation_url: Option<String>, // eth_callBundle endpoint of bundle relays, defaults to url
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EndpointKind {
+    #[serde(rename = "flashbotsBundle")]
+    FlashbotsBundle, // eth_sendBundle, simulated with eth_callBundle first
+    #[serde(rename = "privateRawTransaction")]
+    PrivateRawTransaction, // eth_sendPrivateRawTransaction
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FanOut {
+    #[default]
+    #[serde(rename = "all")]
+    All, // Submit to every endpoint at once
+    #[serde(rename = "failover")]
+    Failover, // Submit to one endpoint at a time, in order, moving on when it did not get the transaction included
 }
 
 #[derive(Deserialize, Clone)]
@@ -36,7 +350,13 @@ pub struct ExchangeConfig {
     #[serde(rename = "baseImplementation")]
     pub base_implementation: String,
     #[serde(rename = "factoryContractAddress")]
-    pub factory_contract_address: String,
+    pub factory_contract_address: Address,
     #[serde(rename = "routerContractAddress")]
-    pub router_contract_address: String,
+    pub router_contract_address: Address,
+    #[serde(rename = "quoterContractAddress", default)]
+    pub quoter_contract_address: Option<Address>, // QuoterV2, required by concentrated liquidity exchanges
+    #[serde(rename = "feeTiers", default)]
+    pub fee_tiers: Vec<u32>, // Tiers searched for a pool when a trade does not name one, every standard tier when empty
+    #[serde(rename = "slippagePolicy", default)]
+    pub slippage_policy: Option<SlippagePolicy>, // Replaces the network's slippage policy for this exchange
 }
\ No newline at end of file
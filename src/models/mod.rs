@@ -1,4 +1,6 @@
 pub mod config_models;
 pub mod security_models;
+pub mod scoring_policy_models;
 pub mod token_api_response_models;
-pub mod processed_trade;
\ No newline at end of file
+pub mod processed_trade;
+pub mod transaction_models;
@@ -1,18 +1,15 @@
-use csv::{Writer, ReaderBuilder, Trim, WriterBuilder};
-use ethers::types::Address;
+use ethers::{types::Address, utils::format_ether};
 use serde::{Deserialize, Serialize};
 use std::error::Error;
-use std::fs::{File, OpenOptions, self};
 use std::option::Option;
-use std::path::Path;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::utils;
-
-const FILE_PATH: &str = "data.csv";
+use crate::{models::{security_models::ScoreBreakdown, transaction_models::{TokenApproval, TxReplacement}}, storage::trade_store};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessedTrade {
+    #[serde(default)]
+    pub id: Option<i64>, // Set once the trade is stored
     // Base info
     pub network_chain_id: String,
     pub network_name: String,
@@ -33,6 +30,14 @@ pub struct ProcessedTrade {
     pub profit_or_loss: Option<f64>,
     pub multiplier: Option<f64>,
     pub bot_wallet_balance: Option<f64>,
+    #[serde(default)]
+    pub simulated: bool, // Filled in dry-run mode, no transaction was sent
+    #[serde(default)]
+    pub score_breakdown: Option<ScoreBreakdown>, // Why the token got its security score
+    #[serde(default)]
+    pub fee_tier: Option<u32>, // Fee of the pool in hundredths of a bip, only concentrated liquidity pools have one
+    #[serde(default)]
+    pub opened_at: Option<u64>, // Unix seconds the buy went through, exit rules count the hold time from it
 }
 
 impl ProcessedTrade {
@@ -44,8 +49,10 @@ impl ProcessedTrade {
         pair_address: Address,
         token_address: Address,
         base_token_address: Address,
+        fee_tier: Option<u32>,
     ) -> Result<Self, Box<dyn Error>> {
-        let trade = ProcessedTrade {
+        let mut trade = ProcessedTrade {
+            id: None,
             network_chain_id,
             network_name,
             exchange_name,
@@ -64,30 +71,54 @@ impl ProcessedTrade {
             profit_or_loss: None,
             multiplier: None,
             bot_wallet_balance: None,
+            simulated: false,
+            score_breakdown: None,
+            fee_tier,
+            opened_at: None,
         };
 
-        // Save the new trade to CSV
-        trade.append_to_csv()?;
+        // Save the new trade to the trade store
+        trade.id = Some(trade_store()?.insert(&trade)?);
 
         Ok(trade)
     }
 
-    // Update to OpenPosition status
-    pub fn open_position(&mut self, amount: f64, security_score: f64) -> Result<(), Box<dyn Error>> {
+    // Record how the security score was reached
+    pub fn scored(&mut self, score_breakdown: ScoreBreakdown) -> Result<(), Box<dyn Error>> {
+        self.security_score = Some(score_breakdown.final_score);
+        self.score_breakdown = Some(score_breakdown);
+        self.last_update = formatted_time();
+        self.persist()
+    }
+
+    // Update to OpenPosition status, manual buys have no security score
+    pub fn open_position(&mut self, amount: f64, security_score: Option<f64>, gas_fee_paid: f64, simulated: bool) -> Result<(), Box<dyn Error>> {
         self.status = TradeStatus::OpenPosition;
         self.amount_bought = Some(amount);
-        self.security_score = Some(security_score);
+        self.security_score = security_score.or(self.security_score);
+        self.gas_fee_paid = Some(gas_fee_paid);
+        self.simulated = simulated;
         self.last_update = formatted_time();
-        self.update_csv()
+        self.opened_at = self.last_update.parse().ok();
+        self.persist()
     }
 
     // Update to ClosedPosition status
-    pub fn closed_position(&mut self, amount_sold: f64, profit_or_loss: f64) -> Result<(), Box<dyn Error>> {
+    pub fn closed_position(&mut self, amount_sold: f64, profit_or_loss: f64, multiplier: f64, gas_fee_paid: f64) -> Result<(), Box<dyn Error>> {
         self.status = TradeStatus::ClosedPosition;
+        self.gas_fee_paid = Some(self.gas_fee_paid.unwrap_or_default() + gas_fee_paid);
         self.amount_sold = Some(amount_sold);
         self.profit_or_loss = Some(profit_or_loss);
+        self.multiplier = Some(multiplier);
+        self.last_update = formatted_time();
+        self.persist()
+    }
+
+    // The position is still open but the bot gave up selling it
+    pub fn close_failed(&mut self) -> Result<(), Box<dyn Error>> {
+        self.substatus = Some(TradeSubStatus::CloseFailed);
         self.last_update = formatted_time();
-        self.update_csv()
+        self.persist()
     }
 
     // Update to Canceled status
@@ -95,113 +126,35 @@ impl ProcessedTrade {
         self.status = TradeStatus::Canceled;
         self.substatus = Some(substatus);
         self.last_update = formatted_time();
-        self.update_csv()
+        self.persist()
     }
 
-    // Internal method to update the CSV with the current state of the trade
-    fn update_csv(&self) -> Result<(), Box<dyn Error>> {
-            // Open the CSV file for reading
-        let file = File::open(FILE_PATH)?;
-        let mut rdr = ReaderBuilder::new()
-            .trim(Trim::All)
-            .from_reader(file);
-
-        // Read all records from the CSV into memory
-        let mut records: Vec<ProcessedTrade> = Vec::new();
-        for result in rdr.deserialize() {
-            let record: ProcessedTrade = result?;
-            records.push(record);
-        }
-
-        // Find and update the relevant record
-        for record in &mut records {
-            if record.pair_address == self.pair_address {
-                // Update the record's fields from the data
-                *record = self.clone();
-                break;  // Assuming only one record will match
-            }
-        }
-
-        // Open the CSV file for writing (this will overwrite the existing file)
-        let mut wtr = WriterBuilder::new().from_path(FILE_PATH)?;
-
-        // Write the updated records back to the CSV
-        for record in records {
-            wtr.serialize(record)?;
+    // Keep the transactions resubmitted for this trade
+    pub fn record_replacements(&self, replacements: &[TxReplacement]) -> Result<(), Box<dyn Error>> {
+        if replacements.is_empty() {
+            return Ok(());
         }
-        wtr.flush()?;
-
-        Ok(())
+        let id = self.id.ok_or("Cannot record replacements of a trade that was never stored")?;
+        trade_store()?.record_replacements(id, replacements)
     }
 
-    // Internal method to write the trade data to the CSV when created
-    fn write_csv(&self) -> Result<(), Box<dyn Error>> {
-        let mut wtr = Writer::from_path(FILE_PATH)?;
-        // Write the ProcessedTrade data to the CSV
-        wtr.serialize(self)?;
-        wtr.flush()?;
-
-        Ok(())
+    // Keep the approval sent before selling, its gas counts towards the trade
+    pub fn approved(&mut self, approval: &TokenApproval) -> Result<(), Box<dyn Error>> {
+        let id = self.id.ok_or("Cannot record an approval of a trade that was never stored")?;
+        trade_store()?.record_approval(id, approval)?;
+        let gas_fee_paid: f64 = format_ether(approval.gas_fee).parse()?;
+        self.gas_fee_paid = Some(self.gas_fee_paid.unwrap_or_default() + gas_fee_paid);
+        self.last_update = formatted_time();
+        self.persist()
     }
 
-    fn append_to_csv(&self) -> Result<(), Box<dyn Error>> {
-        // Check if the file exists
-        let file_exists = Path::new(FILE_PATH).exists();
-    
-        // Open the CSV file in append mode
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(FILE_PATH)?;
-    
-        let mut wtr = WriterBuilder::new().from_writer(file);
-    
-        // If the file doesn't exist, write the header
-        if !file_exists {
-            wtr.write_record(&[
-                "network_chain_id",
-                "network_name",
-                "exchange_name",
-                "pair_address",
-                "token_address",
-                "base_token_address",
-                "last_update",
-                "status",
-                "substatus",
-                "security_score",
-                "amount_bought",
-                "amount_sold",
-                "exchange_fee_paid",
-                "gas_fee_paid",
-                "profit_or_loss",
-                "multiplier",
-                "bot_wallet_balance",
-            ])?;
-        }
-    
-        // Write the ProcessedTrade data to the CSV
-        wtr.serialize(self)?;
-        wtr.flush()?;
-    
-        Ok(())
+    // Internal method to write the current state of the trade to the trade store
+    fn persist(&self) -> Result<(), Box<dyn Error>> {
+        trade_store()?.update(self)
     }
-    
 }
 
-fn is_file_empty(file_path: &str) -> Result<bool, Box<dyn Error>> {
-    // Check if the file exists
-    if !Path::new(file_path).exists() {
-        return Ok(true);
-    }
-
-    // Check if the file is empty (has no content)
-    let metadata = fs::metadata(file_path)?;
-    let is_empty = metadata.len() == 0;
-
-    Ok(is_empty)
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TradeStatus {
     OpenPosition,
     ClosedPosition,
@@ -214,7 +167,13 @@ pub enum TradeSubStatus {
     NotBaseTokenPair,
     ExistingPoolsFound,
     FailedSecurityCheck,
+    FailedHoneypotSimulation,
     InsufficientFunds,
+    TransactionCanceled,
+    LiquidityTimeout, // The pool did not get enough liquidity in time to be assessed
+    UnsupportedTransferTax, // The token is taxed and the exchange cannot swap fee-on-transfer tokens
+    BuyFailed, // Nothing was bought, the buy failed or the trade stopped with an error before it
+    CloseFailed, // Every attempt to sell the open position failed, it has to be sold by hand
     // Add more as needed
 }
 
@@ -222,9 +181,8 @@ fn formatted_time() -> String {
     let now = SystemTime::now();
 
     // Convert SystemTime to a String in a specific format
-    let formatted_time = match now.duration_since(UNIX_EPOCH) {
+    match now.duration_since(UNIX_EPOCH) {
         Ok(duration) => duration.as_secs().to_string(), // Convert to seconds and then to String
         Err(_) => String::from("Invalid time"), // Handle error if SystemTime is earlier than UNIX_EPOCH
-    };
-    formatted_time
+    }
 }
\ No newline at end of file
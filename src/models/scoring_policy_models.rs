@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// Checks reading a "0"/"1" flag of the token security details, flagged when the risky value is reported
+pub const FLAG_CHECKS: &[&str] = &[
+    "notInDex",
+    "noLiquidity",
+    "cannotBuy",
+    "cannotSellAll",
+    "isHoneypot",
+    "honeypotWithSameCreator",
+    "selfdestruct",
+    "hiddenOwner",
+    "ownerChangeBalance",
+    "isProxy",
+    "isMintable",
+    "isAntiWhale",
+    "slippageModifiable",
+    "isBlacklisted",
+    "transferPausable",
+    "tradingCooldown",
+    "closedSource",
+    "externalCall",
+    "personalSlippageModifiable",
+    "isWhitelisted",
+    "antiWhaleModifiable",
+];
+
+// Checks reading a numeric value of the token security details
+pub const VALUE_CHECKS: &[&str] = &[
+    "liquidity",
+    "buyTax",
+    "sellTax",
+    "ownerPercent",
+    "holderCount",
+    "topHolderPercent",
+];
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ScoringPolicy {
+    #[serde(rename = "highRisk")]
+    pub high_risk: CategoryPolicy, // A zero score in this category rejects the token whatever the other categories say
+    pub risky: CategoryPolicy,
+    #[serde(rename = "lowRisk")]
+    pub low_risk: CategoryPolicy,
+    #[serde(rename = "buyThreshold")]
+    pub buy_threshold: f64, // Minimum final score, between 0 and 100, to buy the token
+    #[serde(rename = "tradeSizing")]
+    pub trade_sizing: TradeSizing,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CategoryPolicy {
+    pub weight: f64, // Weight of the category score in the final score
+    pub checks: BTreeMap<String, CheckRule>, // Keyed by check name, see FLAG_CHECKS and VALUE_CHECKS
+}
+
+// Every category score starts at 100 and each check takes its penalty off it.
+// Flag checks apply `penalty` when flagged. Value checks apply `penalty` when the value is above `above`
+// or below `below`, otherwise `value * scale - passBonus`.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CheckRule {
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub penalty: f64,
+    #[serde(rename = "missingPenalty", default)]
+    pub missing_penalty: f64, // Applied when the API did not report the value
+    #[serde(default)]
+    pub veto: bool, // Rejects the token outright when the check fires
+    #[serde(default)]
+    pub above: Option<f64>,
+    #[serde(default)]
+    pub below: Option<f64>,
+    #[serde(default)]
+    pub scale: f64,
+    #[serde(rename = "passBonus", default)]
+    pub pass_bonus: f64,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct TradeSizing {
+    #[serde(rename = "minWalletBalance")]
+    pub min_wallet_balance: f64, // Minimum balance in wallet to consider trading (e.g., 0.1 native tokens)
+    #[serde(rename = "minLiquidity")]
+    pub min_liquidity: f64, // Minimum liquidity in the pool, in USD, to consider trading
+    #[serde(rename = "minConfidence")]
+    pub min_confidence: f64, // Minimum confidence score to size a trade at all
+    #[serde(rename = "minTradeAmount")]
+    pub min_trade_amount: f64, // Amount returned when one of the minimums is not met
+    #[serde(rename = "maxWalletTradePercentage")]
+    pub max_wallet_trade_percentage: f64, // Share of the wallet balance a single trade may use, between 0 and 1
+    #[serde(rename = "maxPriceImpact")]
+    pub max_price_impact: f64, // Maximum price impact of a trade, between 0 and 1
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl CheckRule {
+    fn penalty(penalty: f64) -> Self {
+        CheckRule { enabled: true, penalty, missing_penalty: 0.0, veto: false, above: None, below: None, scale: 0.0, pass_bonus: 0.0 }
+    }
+
+    fn veto() -> Self {
+        CheckRule { veto: true, ..CheckRule::penalty(0.0) }
+    }
+
+    fn missing(mut self, missing_penalty: f64) -> Self {
+        self.missing_penalty = missing_penalty;
+        self
+    }
+}
+
+fn checks(rules: Vec<(&str, CheckRule)>) -> BTreeMap<String, CheckRule> {
+    rules.into_iter().map(|(name, rule)| (name.to_string(), rule)).collect()
+}
+
+impl Default for ScoringPolicy {
+    fn default() -> Self {
+        ScoringPolicy {
+            high_risk: CategoryPolicy {
+                weight: 5.0,
+                checks: checks(vec![
+                    ("notInDex", CheckRule::veto()),
+                    ("noLiquidity", CheckRule::veto()),
+                    ("cannotBuy", CheckRule::veto().missing(30.0)),
+                    ("cannotSellAll", CheckRule::veto().missing(30.0)),
+                    ("isHoneypot", CheckRule::veto().missing(30.0)),
+                    ("honeypotWithSameCreator", CheckRule::veto()),
+                    ("selfdestruct", CheckRule::veto()),
+                    ("hiddenOwner", CheckRule::veto()),
+                    ("ownerChangeBalance", CheckRule::veto()),
+                    ("isProxy", CheckRule::penalty(50.0)),
+                    ("isMintable", CheckRule::penalty(50.0)),
+                ]),
+            },
+            risky: CategoryPolicy {
+                weight: 3.0,
+                checks: checks(vec![
+                    ("liquidity", CheckRule { below: Some(20000.0), pass_bonus: 5.0, ..CheckRule::penalty(20.0) }.missing(5.0)),
+                    ("buyTax", CheckRule { scale: 100.0, ..CheckRule::penalty(0.0) }),
+                    ("sellTax", CheckRule { scale: 100.0, ..CheckRule::penalty(0.0) }),
+                    ("isAntiWhale", CheckRule::penalty(10.0).missing(5.0)),
+                    ("slippageModifiable", CheckRule::penalty(15.0).missing(5.0)),
+                    ("ownerPercent", CheckRule { above: Some(50.0), scale: 0.5, ..CheckRule::penalty(30.0) }),
+                    ("isBlacklisted", CheckRule::penalty(20.0).missing(5.0)),
+                    ("transferPausable", CheckRule::penalty(10.0).missing(5.0)),
+                    ("tradingCooldown", CheckRule::penalty(10.0).missing(5.0)),
+                ]),
+            },
+            low_risk: CategoryPolicy {
+                weight: 1.0,
+                checks: checks(vec![
+                    ("isProxy", CheckRule::penalty(5.0).missing(2.0)),
+                    ("closedSource", CheckRule::penalty(5.0).missing(2.0)),
+                    ("externalCall", CheckRule::penalty(5.0).missing(2.0)),
+                    ("personalSlippageModifiable", CheckRule::penalty(5.0).missing(2.0)),
+                    ("holderCount", CheckRule { below: Some(10.0), ..CheckRule::penalty(5.0) }.missing(2.0)),
+                    ("isWhitelisted", CheckRule::penalty(5.0).missing(2.0)),
+                    ("antiWhaleModifiable", CheckRule::penalty(5.0).missing(2.0)),
+                    ("topHolderPercent", CheckRule { above: Some(50.0), ..CheckRule::penalty(5.0) }),
+                ]),
+            },
+            buy_threshold: 70.0,
+            trade_sizing: TradeSizing::default(),
+        }
+    }
+}
+
+impl Default for TradeSizing {
+    fn default() -> Self {
+        TradeSizing {
+            min_wallet_balance: 0.1,
+            min_liquidity: 1000.0,
+            min_confidence: 50.0,
+            min_trade_amount: 0.0,
+            max_wallet_trade_percentage: 0.50,
+            max_price_impact: 0.01,
+        }
+    }
+}
+
+impl ScoringPolicy {
+    // Checks the policy is usable, the error names the offending field
+    pub fn validate(&self) -> Result<(), String> {
+        for (category_name, category) in [("highRisk", &self.high_risk), ("risky", &self.risky), ("lowRisk", &self.low_risk)] {
+            category.validate().map_err(|e| format!("{}.{}", category_name, e))?;
+        }
+        if self.high_risk.weight + self.risky.weight + self.low_risk.weight <= 0.0 {
+            return Err("the category weights must not all be zero".to_string());
+        }
+        if !(0.0..=100.0).contains(&self.buy_threshold) {
+            return Err(format!("buyThreshold: {} is not between 0 and 100", self.buy_threshold));
+        }
+        self.trade_sizing.validate().map_err(|e| format!("tradeSizing.{}", e))
+    }
+}
+
+impl CategoryPolicy {
+    fn validate(&self) -> Result<(), String> {
+        if !self.weight.is_finite() || self.weight < 0.0 {
+            return Err(format!("weight: {} must be a non-negative number", self.weight));
+        }
+        for (name, rule) in &self.checks {
+            rule.validate(name).map_err(|e| format!("checks.{}: {}", name, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl CheckRule {
+    fn validate(&self, name: &str) -> Result<(), String> {
+        let is_value_check = VALUE_CHECKS.contains(&name);
+        if !is_value_check && !FLAG_CHECKS.contains(&name) {
+            return Err("unknown check".to_string());
+        }
+        for (field, value) in [("penalty", self.penalty), ("missingPenalty", self.missing_penalty), ("passBonus", self.pass_bonus), ("scale", self.scale)] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("{} must be a non-negative number", field));
+            }
+        }
+        if !is_value_check && (self.above.is_some() || self.below.is_some() || self.scale != 0.0 || self.pass_bonus != 0.0) {
+            return Err("above, below, scale and passBonus only apply to value checks".to_string());
+        }
+        if let (Some(above), Some(below)) = (self.above, self.below) {
+            if below > above {
+                return Err(format!("below ({}) is greater than above ({}), the check would always fire", below, above));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TradeSizing {
+    fn validate(&self) -> Result<(), String> {
+        for (field, value) in [
+            ("minWalletBalance", self.min_wallet_balance),
+            ("minLiquidity", self.min_liquidity),
+            ("minConfidence", self.min_confidence),
+            ("minTradeAmount", self.min_trade_amount),
+        ] {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("{}: {} must be a non-negative number", field, value));
+            }
+        }
+        for (field, value) in [("maxWalletTradePercentage", self.max_wallet_trade_percentage), ("maxPriceImpact", self.max_price_impact)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("{}: {} is not between 0 and 1", field, value));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_risky_check(name: &str, rule: CheckRule) -> ScoringPolicy {
+        let mut policy = ScoringPolicy::default();
+        policy.risky.checks.insert(name.to_string(), rule);
+        policy
+    }
+
+    #[test]
+    fn default_policy_is_valid() {
+        assert_eq!(ScoringPolicy::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn below_greater_than_above_is_rejected() {
+        let policy = with_risky_check("ownerPercent", CheckRule { above: Some(10.0), below: Some(20.0), ..CheckRule::penalty(30.0) });
+        let error = policy.validate().unwrap_err();
+        assert!(error.starts_with("risky.checks.ownerPercent: below (20) is greater than above (10)"), "{}", error);
+
+        // Equal bounds leave exactly one passing value
+        let policy = with_risky_check("ownerPercent", CheckRule { above: Some(10.0), below: Some(10.0), ..CheckRule::penalty(30.0) });
+        assert_eq!(policy.validate(), Ok(()));
+    }
+
+    #[test]
+    fn unknown_checks_are_rejected() {
+        let error = with_risky_check("isRugPull", CheckRule::penalty(10.0)).validate().unwrap_err();
+        assert_eq!(error, "risky.checks.isRugPull: unknown check");
+    }
+
+    #[test]
+    fn value_fields_are_rejected_on_flag_checks() {
+        let policy = with_risky_check("isBlacklisted", CheckRule { above: Some(1.0), ..CheckRule::penalty(10.0) });
+        assert!(policy.validate().unwrap_err().contains("only apply to value checks"));
+    }
+
+    #[test]
+    fn negative_and_non_finite_numbers_are_rejected() {
+        let error = with_risky_check("isBlacklisted", CheckRule::penalty(-1.0)).validate().unwrap_err();
+        assert_eq!(error, "risky.checks.isBlacklisted: penalty must be a non-negative number");
+        let error = with_risky_check("isBlacklisted", CheckRule::penalty(0.0).missing(f64::NAN)).validate().unwrap_err();
+        assert_eq!(error, "risky.checks.isBlacklisted: missingPenalty must be a non-negative number");
+
+        let mut policy = ScoringPolicy::default();
+        policy.low_risk.weight = f64::INFINITY;
+        assert!(policy.validate().unwrap_err().starts_with("lowRisk.weight"));
+    }
+
+    #[test]
+    fn all_zero_weights_are_rejected() {
+        let mut policy = ScoringPolicy::default();
+        policy.high_risk.weight = 0.0;
+        policy.risky.weight = 0.0;
+        policy.low_risk.weight = 0.0;
+        assert_eq!(policy.validate(), Err("the category weights must not all be zero".to_string()));
+    }
+
+    #[test]
+    fn thresholds_out_of_range_are_rejected() {
+        let policy = ScoringPolicy { buy_threshold: 101.0, ..ScoringPolicy::default() };
+        assert_eq!(policy.validate(), Err("buyThreshold: 101 is not between 0 and 100".to_string()));
+
+        let mut policy = ScoringPolicy::default();
+        policy.trade_sizing.max_wallet_trade_percentage = 1.5;
+        assert_eq!(policy.validate(), Err("tradeSizing.maxWalletTradePercentage: 1.5 is not between 0 and 1".to_string()));
+    }
+}
@@ -1,4 +1,58 @@
+use serde::{Deserialize, Serialize};
+
 pub struct TokenAssessment {
     pub confidence_score: f64, // Score between 0.0 to 1.0, where 1.0 is highest confidence
+    pub score_breakdown: ScoreBreakdown, // How the API data led to the score
     pub recommended_trade_amount: f64, // Suggested percentage of the bot's wallet to use for trade
-}
\ No newline at end of file
+    pub honeypot_simulation: Option<HoneypotSimulation>, // None when the round trip could not be simulated
+    pub transfer_tax: TransferTax,
+}
+
+// Share of a transfer the token takes as a fee, between 0.0 and 1.0
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TransferTax {
+    pub buy: f64,
+    pub sell: f64,
+}
+
+impl TransferTax {
+    // Taxed tokens deliver less than the router computes, plain swaps revert on them
+    pub fn is_taxed(&self) -> bool {
+        self.buy > 0.0 || self.sell > 0.0
+    }
+}
+
+// Outcome of the simulated buy and immediate sell of a token
+#[derive(Debug, Clone)]
+pub struct HoneypotSimulation {
+    pub buy_failed: bool,
+    pub sell_failed: bool,
+    pub buy_tax: Option<f64>, // Share of the bought tokens that did not arrive, between 0.0 and 1.0
+    pub sell_tax: Option<f64>, // Share of the sale proceeds that did not arrive, between 0.0 and 1.0
+}
+
+impl HoneypotSimulation {
+    pub fn is_honeypot(&self) -> bool {
+        self.buy_failed || self.sell_failed
+    }
+}
+
+// Why a token got its security score, kept with the trade for later audits
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub high_risk_score: f64, // Category scores between 0 and 100, before weighting
+    pub risky_score: f64,
+    pub low_risk_score: f64,
+    pub final_score: f64,
+    pub fired_checks: Vec<FiredCheck>, // Every check that changed a category score or vetoed the token
+    pub veto_reason: Option<String>, // Set when the token was rejected regardless of the other checks
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiredCheck {
+    pub category: String,
+    pub check: String,
+    pub observed: Option<String>, // Value reported by the API, None when it was missing
+    pub penalty: f64, // Points taken off the category score, negative for a bonus
+    pub veto: bool,
+}
@@ -0,0 +1,31 @@
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplacementKind {
+    FeeBump, // Same transaction with higher fees
+    Cancel, // Zero-value transfer to the wallet itself
+}
+
+// A transaction resubmitted at the same nonce because the previous one was not included in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TxReplacement {
+    pub kind: ReplacementKind,
+    pub nonce: U256,
+    pub replaced_tx_hash: H256,
+    pub tx_hash: H256,
+    pub max_fee_per_gas: U256, // Gas price for legacy transactions
+    pub max_priority_fee_per_gas: Option<U256>, // None for legacy transactions
+    pub sent_at: u64, // Unix seconds
+}
+
+// An ERC-20 approval sent so the router can spend the tokens of a sell
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenApproval {
+    pub token: Address,
+    pub spender: Address,
+    pub amount: U256,
+    pub tx_hash: H256,
+    pub gas_fee: U256, // Gas paid in wei
+    pub approved_at: u64, // Unix seconds
+}
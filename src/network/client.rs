@@ -1,34 +1,32 @@
 use std::{sync::Arc, error::Error};
-use std::env;
-use std::str::FromStr;
-use ethers::core::k256::ecdsa::SigningKey;
-use ethers::signers::{LocalWallet, Signer};
-use ethers::{providers::{Provider, Http}, signers::Wallet, middleware::SignerMiddleware};
+use ethers::signers::LocalWallet;
+use ethers::{providers::{Provider, Http}, middleware::SignerMiddleware};
 use ethers_flashbots::FlashbotsMiddleware;
 use reqwest::Url;
 
-pub async fn create_client_arc(rpc_url: &str, chain_id: String) -> Result<Arc<SignerMiddleware<Provider<Http>, LocalWallet>>, Box<dyn Error>> {
-    let private_key = env::var("PRIVATE_KEY")?;
+use crate::models::config_models::{PrivateEndpoint, SignerConfig};
+
+use super::signer::load_signer;
+
+pub async fn create_client_arc(rpc_url: &str, chain_id: u64, signer: &SignerConfig) -> Result<Arc<SignerMiddleware<Provider<Http>, LocalWallet>>, Box<dyn Error>> {
     let provider = Provider::<Http>::try_from(rpc_url)?;
-    let wallet = Wallet::from_str(&private_key)?.with_chain_id(chain_id.parse::<u64>()?);
+    let wallet = load_signer(signer, chain_id)?;
     let client = SignerMiddleware::new(provider, wallet);
     Ok(Arc::new(client))
 }
 
-pub async fn create_flashbot_client(rpc_url: &str) -> Result<Arc<SignerMiddleware<FlashbotsMiddleware<Provider<Http>, Wallet<SigningKey>>, Wallet<SigningKey>>>, Box<dyn Error>> {
-    let private_key = env::var("PRIVATE_KEY")?;
+pub fn create_flashbot_client(rpc_url: &str, chain_id: u64, endpoint: &PrivateEndpoint, identity: &SignerConfig) -> Result<FlashbotsMiddleware<Provider<Http>, LocalWallet>, Box<dyn Error>> {
     let provider = Provider::<Http>::try_from(rpc_url)?;
     // This is your searcher identity
-    let wallet = LocalWallet::from_str(&private_key)?;
-    let bundle_signer = LocalWallet::from_str(&private_key)?;
-    let client = SignerMiddleware::new(
-        FlashbotsMiddleware::new(
-            provider,
-            Url::parse("https://relay.flashbots.net")?,
-            bundle_signer,
-        ),
-        wallet,
+    let bundle_signer = load_signer(identity, chain_id)?;
+    let mut client = FlashbotsMiddleware::new(
+        provider,
+        Url::parse(&endpoint.url)?,
+        bundle_signer,
     );
-    
-    Ok(Arc::new(client))
-}
\ No newline at end of file
+    if let Some(simulation_url) = &endpoint.simulation_url {
+        client.set_simulation_relay(Url::parse(simulation_url)?);
+    }
+
+    Ok(client)
+}
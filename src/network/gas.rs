@@ -0,0 +1,141 @@
+use std::error::Error;
+
+use ethers::{
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, BlockNumber, Eip1559TransactionRequest, TransactionRequest, U256},
+    utils::{format_ether, parse_ether, parse_units},
+};
+
+use crate::{models::config_models::{GasPolicy, UrgencyPolicy}, utils::formatter::u256_to_f64};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Snipe, // Entering a position, racing other buyers
+    Exit, // Leaving a position
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum GasFees {
+    Eip1559 { max_fee_per_gas: U256, max_priority_fee_per_gas: U256, base_fee: U256 },
+    Legacy { gas_price: U256 },
+}
+
+impl GasFees {
+    // Price per gas the transaction is expected to pay once included
+    pub fn expected_price(&self) -> U256 {
+        match self {
+            GasFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas, base_fee } => {
+                (*base_fee + *max_priority_fee_per_gas).min(*max_fee_per_gas)
+            },
+            GasFees::Legacy { gas_price } => *gas_price,
+        }
+    }
+
+    // Highest price per gas the transaction may pay
+    pub fn max_price(&self) -> U256 {
+        match self {
+            GasFees::Eip1559 { max_fee_per_gas, .. } => *max_fee_per_gas,
+            GasFees::Legacy { gas_price } => *gas_price,
+        }
+    }
+}
+
+pub struct GasStrategy<'a> {
+    policy: &'a GasPolicy,
+}
+
+impl<'a> GasStrategy<'a> {
+    pub fn new(policy: &'a GasPolicy) -> Self {
+        GasStrategy { policy }
+    }
+
+    // Prices the next transaction from the recent fee history, or from eth_gasPrice on chains without EIP-1559
+    pub async fn fees<M: Middleware>(&self, client: &M, urgency: Urgency) -> Result<GasFees, Box<dyn Error>>
+    where
+        M::Error: 'static,
+    {
+        let urgency_policy = self.urgency_policy(urgency);
+
+        if self.policy.eip1559 {
+            match client.fee_history(self.policy.fee_history_blocks, BlockNumber::Latest, &[urgency_policy.priority_fee_percentile]).await {
+                // The last base fee is the one of the next block
+                Ok(history) => if let Some(base_fee) = history.base_fee_per_gas.last().copied().filter(|base_fee| !base_fee.is_zero()) {
+                    let mut rewards: Vec<U256> = history.reward.iter().filter_map(|block| block.first().copied()).collect();
+                    rewards.sort();
+                    let median_reward = rewards.get(rewards.len() / 2).copied().unwrap_or_default();
+
+                    let min_priority_fee: U256 = parse_units(urgency_policy.min_priority_fee_gwei, "gwei")?.into();
+                    let max_priority_fee_per_gas = median_reward.max(min_priority_fee);
+                    let max_fee_per_gas = scale(base_fee, urgency_policy.base_fee_multiplier) + max_priority_fee_per_gas;
+                    return Ok(GasFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas, base_fee });
+                },
+                Err(e) => log::warn!("eth_feeHistory unavailable, falling back to legacy gas pricing: {}", e),
+            }
+        }
+
+        let gas_price = client.get_gas_price().await?;
+        Ok(GasFees::Legacy { gas_price: scale(gas_price, urgency_policy.legacy_gas_price_multiplier) })
+    }
+
+    // Lowers the fees so gas_limit * max price stays within the per-trade cap, fails when the cap cannot cover the base fee
+    pub fn cap(&self, fees: GasFees, gas_limit: U256) -> Result<GasFees, Box<dyn Error>> {
+        let Some(max_fee_per_trade) = self.policy.max_fee_per_trade else {
+            return Ok(fees);
+        };
+        let max_cost = parse_ether(max_fee_per_trade)?;
+        if gas_limit.is_zero() || fees.max_price() * gas_limit <= max_cost {
+            return Ok(fees);
+        }
+
+        let capped_price = max_cost / gas_limit;
+        let capped = match fees {
+            GasFees::Eip1559 { max_priority_fee_per_gas, base_fee, .. } => {
+                if capped_price <= base_fee {
+                    return Err(format!(
+                        "Gas cap of {} per trade cannot cover the base fee ({} gas at {} wei)",
+                        max_fee_per_trade, gas_limit, base_fee,
+                    ).into());
+                }
+                GasFees::Eip1559 {
+                    max_fee_per_gas: capped_price,
+                    max_priority_fee_per_gas: max_priority_fee_per_gas.min(capped_price - base_fee),
+                    base_fee,
+                }
+            },
+            GasFees::Legacy { .. } => GasFees::Legacy { gas_price: capped_price },
+        };
+        log::warn!("Gas fees capped to {} per trade ({} wei per gas)", format_ether(max_cost), capped_price);
+        Ok(capped)
+    }
+
+    fn urgency_policy(&self, urgency: Urgency) -> &UrgencyPolicy {
+        match urgency {
+            Urgency::Snipe => &self.policy.snipe,
+            Urgency::Exit => &self.policy.exit,
+        }
+    }
+}
+
+// Turns a fee-less request into a priced transaction of the matching type
+pub fn priced_transaction(tx_request: TransactionRequest, fees: GasFees) -> TypedTransaction {
+    match fees {
+        GasFees::Eip1559 { max_fee_per_gas, max_priority_fee_per_gas, .. } => {
+            let mut tx = Eip1559TransactionRequest::new()
+                .max_fee_per_gas(max_fee_per_gas)
+                .max_priority_fee_per_gas(max_priority_fee_per_gas);
+            tx.from = tx_request.from;
+            tx.to = tx_request.to;
+            tx.gas = tx_request.gas;
+            tx.value = tx_request.value;
+            tx.data = tx_request.data;
+            tx.nonce = tx_request.nonce;
+            tx.chain_id = tx_request.chain_id;
+            tx.into()
+        },
+        GasFees::Legacy { gas_price } => tx_request.gas_price(gas_price).into(),
+    }
+}
+
+fn scale(value: U256, multiplier: f64) -> U256 {
+    U256::from((u256_to_f64(value) * multiplier) as u128)
+}
@@ -1,2 +1,6 @@
 pub mod client;
-pub mod transaction;
\ No newline at end of file
+pub mod transaction;
+pub mod private_submission;
+pub mod nonce_manager;
+pub mod gas;
+pub mod signer;
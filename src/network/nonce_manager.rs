@@ -0,0 +1,118 @@
+use std::{collections::{BTreeSet, HashMap}, error::Error, sync::{Arc, Mutex, OnceLock}};
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, U256},
+};
+use tokio::sync::Mutex as AsyncMutex;
+
+// One manager per (chain id, wallet), shared by every task sending from that wallet
+type NonceManagers = Mutex<HashMap<(u64, Address), Arc<NonceManager>>>;
+static NONCE_MANAGERS: OnceLock<NonceManagers> = OnceLock::new();
+
+pub fn nonce_manager(chain_id: u64, wallet: Address) -> Arc<NonceManager> {
+    let managers = NONCE_MANAGERS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut managers = managers.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    managers
+        .entry((chain_id, wallet))
+        .or_insert_with(|| Arc::new(NonceManager::new(wallet)))
+        .clone()
+}
+
+pub struct NonceManager {
+    wallet: Address,
+    state: AsyncMutex<NonceState>,
+}
+
+#[derive(Default)]
+struct NonceState {
+    next: Option<U256>, // Next fresh nonce, None until synced with the node
+    in_flight: BTreeSet<U256>, // Reserved and not yet confirmed or released
+    gaps: BTreeSet<U256>, // Released below `next`, handed out again first so later nonces are not stuck
+}
+
+impl NonceManager {
+    fn new(wallet: Address) -> Self {
+        NonceManager { wallet, state: AsyncMutex::new(NonceState::default()) }
+    }
+
+    // Reserves the lowest nonce nobody else holds
+    pub async fn reserve<M: Middleware>(&self, client: &M) -> Result<U256, Box<dyn Error>>
+    where
+        M::Error: 'static,
+    {
+        let mut state = self.state.lock().await;
+        if state.next.is_none() {
+            let pending = self.pending_count(client).await?;
+            state.next = Some(pending);
+        }
+
+        let nonce = match state.gaps.pop_first() {
+            Some(gap) => gap,
+            None => {
+                let next = state.next.unwrap_or_default();
+                state.next = Some(next + 1);
+                next
+            },
+        };
+        state.in_flight.insert(nonce);
+        log::debug!("[{:?}] Reserved nonce {}", self.wallet, nonce);
+        Ok(nonce)
+    }
+
+    // The transaction using the nonce was mined
+    pub async fn confirm(&self, nonce: U256) {
+        let mut state = self.state.lock().await;
+        state.in_flight.remove(&nonce);
+    }
+
+    // The transaction using the nonce was never broadcast, the nonce can be used again
+    pub async fn release(&self, nonce: U256) {
+        let mut state = self.state.lock().await;
+        if !state.in_flight.remove(&nonce) {
+            return;
+        }
+        match state.next {
+            Some(next) if next == nonce + 1 => state.next = Some(nonce),
+            _ => {
+                log::warn!("[{:?}] Nonce {} released below later reservations, filling the gap first", self.wallet, nonce);
+                state.gaps.insert(nonce);
+            },
+        }
+    }
+
+    // Realigns with the node after a transaction was dropped or replaced, or the wallet sent from elsewhere
+    pub async fn resync<M: Middleware>(&self, client: &M) -> Result<(), Box<dyn Error>>
+    where
+        M::Error: 'static,
+    {
+        let mut state = self.state.lock().await;
+        let pending = self.pending_count(client).await?;
+
+        // Everything below the pending count is used up on chain or in the mempool
+        state.in_flight.retain(|nonce| *nonce >= pending);
+        state.gaps.retain(|nonce| *nonce >= pending);
+
+        // Nonces above the pending count that nobody holds anymore were dropped, hand them out again
+        let highest_held = state.in_flight.last().map(|nonce| *nonce + 1).unwrap_or(pending).max(pending);
+        for nonce in pending.as_u64()..highest_held.as_u64() {
+            let nonce = U256::from(nonce);
+            if !state.in_flight.contains(&nonce) {
+                state.gaps.insert(nonce);
+            }
+        }
+
+        if state.next != Some(highest_held) {
+            log::info!("[{:?}] Nonce resynced from {:?} to {}", self.wallet, state.next, highest_held);
+        }
+        state.next = Some(highest_held);
+        Ok(())
+    }
+
+    async fn pending_count<M: Middleware>(&self, client: &M) -> Result<U256, Box<dyn Error>>
+    where
+        M::Error: 'static,
+    {
+        Ok(client.get_transaction_count(self.wallet, Some(BlockNumber::Pending.into())).await?)
+    }
+}
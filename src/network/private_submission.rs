@@ -0,0 +1,244 @@
+use std::{sync::Arc, error::Error, time::Duration};
+
+use ethers::{
+    providers::{Provider, Http, Middleware},
+    signers::{LocalWallet, Signer},
+    types::{transaction::eip2718::TypedTransaction, BlockNumber, Bytes, TransactionReceipt, H256, U256, U64},
+    middleware::SignerMiddleware,
+    utils::keccak256,
+};
+use ethers_flashbots::{BundleRequest, FlashbotsMiddleware, SimulatedBundle};
+use tokio::time::sleep;
+
+use crate::models::config_models::{NetworkMetadata, PrivateEndpoint, EndpointKind, FanOut};
+
+use super::{client::create_flashbot_client, nonce_manager::nonce_manager, transaction::{cancel_private, SentTransaction}};
+
+const BLOCK_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const BLOCK_TIME_SECS: u64 = 12;
+
+// Reads the amount out from the return data of the submitted call
+pub type OutputDecoder<'a> = dyn Fn(&Bytes) -> Result<U256, Box<dyn Error>> + Sync + 'a;
+
+// Output the swap must produce in the bundle simulation for the transaction to be submitted
+pub struct BundleOutputCheck<'a> {
+    pub min_amount_out: U256,
+    pub decode: &'a OutputDecoder<'a>,
+}
+
+enum Relay {
+    Bundle(Box<FlashbotsMiddleware<Provider<Http>, LocalWallet>>),
+    RawTransaction(Provider<Http>),
+}
+
+struct Route<'a> {
+    endpoint: &'a PrivateEndpoint,
+    relay: Relay,
+}
+
+// Sends the signed transaction to the network's private endpoints only, never to the public mempool
+pub async fn send_private(
+    network_metadata: &NetworkMetadata,
+    client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
+    mut tx: TypedTransaction,
+    output_check: BundleOutputCheck<'_>,
+) -> Result<SentTransaction, Box<dyn Error>> {
+    let submission = &network_metadata.private_submission;
+    let target_blocks = submission.target_blocks.max(1);
+    let routes = submission.endpoints
+        .iter()
+        .map(|endpoint| route(network_metadata, endpoint))
+        .collect::<Result<Vec<Route>, Box<dyn Error>>>()?;
+
+    // Private routes share the wallet's nonce sequence with the public mempool path
+    let nonces = nonce_manager(client.signer().chain_id(), client.address());
+    let nonce = nonces.reserve(client.as_ref()).await?;
+    tx.set_nonce(nonce);
+
+    let signature = match client.signer().sign_transaction(&tx).await {
+        Ok(signature) => signature,
+        Err(e) => {
+            nonces.release(nonce).await;
+            return Err(Box::new(e));
+        },
+    };
+    let raw_tx = tx.rlp_signed(&signature);
+    let tx_hash = H256::from(keccak256(&raw_tx));
+
+    // Bundles are simulated once, on the first bundle endpoint
+    let bundle_relay = routes.iter().find_map(|route| match &route.relay {
+        Relay::Bundle(flashbots) => Some(flashbots),
+        Relay::RawTransaction(_) => None,
+    });
+    if let Some(flashbots) = bundle_relay {
+        let latest_block = match client.get_block(BlockNumber::Latest).await {
+            Ok(Some(block)) => block,
+            Ok(None) => {
+                nonces.release(nonce).await;
+                return Err("Failed to get the latest block".into());
+            },
+            Err(e) => {
+                nonces.release(nonce).await;
+                return Err(Box::new(e));
+            },
+        };
+        let latest_block_number = latest_block.number.unwrap_or_default();
+        let bundle = BundleRequest::new()
+            .push_transaction(raw_tx.clone())
+            .set_block(latest_block_number + 1)
+            .set_simulation_block(latest_block_number)
+            .set_simulation_timestamp(latest_block.timestamp.as_u64() + BLOCK_TIME_SECS);
+
+        let simulation = match flashbots.simulate_bundle(&bundle).await {
+            Ok(simulation) => simulation,
+            Err(e) => {
+                nonces.release(nonce).await;
+                return Err(Box::new(e));
+            },
+        };
+        if let Some(rejection) = simulation_rejection(&simulation, &output_check) {
+            nonces.release(nonce).await;
+            return Err(format!("[{}] Bundle rejected after simulation: {}", network_metadata.name, rejection).into());
+        }
+        log::info!(
+            "[{}] Bundle simulated at block {}: {} gas, effective gas price {} wei",
+            network_metadata.name, simulation.simulation_block, simulation.gas_used, simulation.effective_gas_price(),
+        );
+    }
+
+    // Every round submits the same signed transaction, so whichever endpoint gets it included the hash is the same
+    let rounds: Vec<&[Route]> = match submission.fan_out {
+        FanOut::All => vec![&routes[..]],
+        FanOut::Failover => routes.chunks(1).collect(),
+    };
+    // Raw transaction endpoints keep the transaction in their pool, it can be included past the target blocks
+    let mut raw_tx_sent = false;
+    for round in rounds {
+        let from_block = match client.get_block_number().await {
+            Ok(from_block) => from_block,
+            Err(e) => {
+                log::warn!("[{}] Failed to fetch the block number, stopping the private submission: {}", network_metadata.name, e);
+                break;
+            },
+        };
+        let mut accepted = 0;
+        for route in round {
+            match submit(route, &raw_tx, from_block, target_blocks).await {
+                Ok(()) => {
+                    log::info!(
+                        "[{}] Transaction {:?} submitted to {} for blocks {} to {}",
+                        network_metadata.name, tx_hash, route.endpoint.name, from_block + 1, from_block + target_blocks,
+                    );
+                    accepted += 1;
+                    raw_tx_sent |= matches!(route.relay, Relay::RawTransaction(_));
+                },
+                Err(e) => log::warn!("[{}] Submission to {} failed: {}", network_metadata.name, route.endpoint.name, e),
+            }
+        }
+        if accepted == 0 {
+            continue;
+        }
+
+        let receipt = wait_for_inclusion(client.as_ref(), tx_hash, from_block + target_blocks).await;
+        if let Some(receipt) = receipt {
+            log::info!("[{}] Transaction {:?} included in block {:?}", network_metadata.name, tx_hash, receipt.block_number);
+            nonces.confirm(nonce).await;
+            return Ok(SentTransaction { receipt, replacements: Vec::new(), canceled: false });
+        }
+        let endpoint_names: Vec<&str> = round.iter().map(|route| route.endpoint.name.as_str()).collect();
+        log::info!(
+            "[{}] Transaction {:?} not included through {} by block {}",
+            network_metadata.name, tx_hash, endpoint_names.join(", "), from_block + target_blocks,
+        );
+    }
+
+    // The transaction may still be pending at a raw transaction endpoint, free the nonce with a cancellation
+    // and report whichever of the two gets mined
+    if raw_tx_sent {
+        return cancel_private(network_metadata, client, tx, tx_hash).await;
+    }
+
+    // Bundles expire after their target blocks, the nonce was never used
+    nonces.resync(client.as_ref()).await?;
+    Err(format!("[{}] Transaction {:?} was not included through any private endpoint", network_metadata.name, tx_hash).into())
+}
+
+fn route<'a>(network_metadata: &NetworkMetadata, endpoint: &'a PrivateEndpoint) -> Result<Route<'a>, Box<dyn Error>> {
+    let relay = match endpoint.kind {
+        // Bundle relays read the chain through the network's own RPC
+        EndpointKind::FlashbotsBundle => {
+            let identity = network_metadata.private_submission.identity.as_ref().unwrap_or(&network_metadata.signer);
+            Relay::Bundle(Box::new(create_flashbot_client(network_metadata.rpc_url.as_str(), network_metadata.chain_id, endpoint, identity)?))
+        },
+        EndpointKind::PrivateRawTransaction => Relay::RawTransaction(Provider::<Http>::try_from(endpoint.url.as_str())?),
+    };
+    Ok(Route { endpoint, relay })
+}
+
+// Hands the signed transaction to the endpoint, bundles are sent once per target block
+async fn submit(route: &Route<'_>, raw_tx: &Bytes, from_block: U64, target_blocks: u64) -> Result<(), Box<dyn Error>> {
+    match &route.relay {
+        Relay::Bundle(flashbots) => {
+            let mut accepted = false;
+            for target_block in (1..=target_blocks).map(|offset| from_block + offset) {
+                let bundle = BundleRequest::new()
+                    .push_transaction(raw_tx.clone())
+                    .set_block(target_block);
+                match flashbots.send_bundle(&bundle).await {
+                    Ok(_) => accepted = true,
+                    Err(e) => log::warn!("Relay {} rejected the bundle for block {}: {}", route.endpoint.name, target_block, e),
+                }
+            }
+            if !accepted {
+                return Err("Bundle was not accepted for any target block".into());
+            }
+        },
+        Relay::RawTransaction(provider) => {
+            let _: H256 = provider.request("eth_sendPrivateRawTransaction", [raw_tx]).await?;
+        },
+    }
+    Ok(())
+}
+
+// Polls for the receipt until the last target block is mined, None when the transaction did not make it.
+// A failed poll is only logged, the transaction may still be included
+async fn wait_for_inclusion<M: Middleware>(client: &M, tx_hash: H256, last_block: U64) -> Option<TransactionReceipt> {
+    loop {
+        // Read the block before the receipt, a transaction mined in between is caught on the next poll
+        let past_last_block = match client.get_block_number().await {
+            Ok(block_number) => block_number > last_block,
+            Err(e) => {
+                log::warn!("Failed to fetch the block number while waiting for {:?}: {}", tx_hash, e);
+                false
+            },
+        };
+        match client.get_transaction_receipt(tx_hash).await {
+            Ok(Some(receipt)) => return Some(receipt),
+            Ok(None) if past_last_block => return None,
+            Ok(None) => (),
+            Err(e) => log::warn!("Failed to fetch the receipt of {:?}: {}", tx_hash, e),
+        }
+        sleep(BLOCK_POLL_INTERVAL).await;
+    }
+}
+
+// Reason to drop the bundle, None when every transaction succeeded and the swap returned enough
+fn simulation_rejection(simulation: &SimulatedBundle, output_check: &BundleOutputCheck) -> Option<String> {
+    for simulated_tx in &simulation.transactions {
+        if let Some(reason) = simulated_tx.revert.as_ref().or(simulated_tx.error.as_ref()) {
+            return Some(format!("transaction {:?} reverted: {}", simulated_tx.hash, reason));
+        }
+    }
+
+    // Swaps without return data, like the fee-on-transfer variants, are still bound by the amountOutMin of their calldata
+    let output = simulation.transactions.last()
+        .and_then(|simulated_tx| simulated_tx.value.as_ref())
+        .filter(|output| !output.is_empty())?;
+    match (output_check.decode)(output) {
+        Ok(amount_out) if amount_out < output_check.min_amount_out => {
+            Some(format!("output {} is below the minimum {}", amount_out, output_check.min_amount_out))
+        },
+        Ok(_) => None,
+        Err(e) => Some(format!("failed to decode the swap output: {}", e)),
+    }
+}
@@ -0,0 +1,82 @@
+use std::{collections::HashMap, env, error::Error, io::{self, BufRead, IsTerminal, Write}, process::Command, str::FromStr, sync::{Mutex, OnceLock}};
+
+use ethers::signers::{LocalWallet, Signer};
+
+use crate::{
+    models::config_models::{NetworkMetadata, SignerConfig},
+    trading::executor::is_dry_run,
+};
+
+// Loaded wallets per signer config, so a keystore is decrypted and its password asked for only once
+static SIGNERS: OnceLock<Mutex<HashMap<SignerConfig, LocalWallet>>> = OnceLock::new();
+
+// Wallet of the signer config, bound to the chain it signs for
+pub fn load_signer(signer: &SignerConfig, chain_id: u64) -> Result<LocalWallet, Box<dyn Error>> {
+    let signers = SIGNERS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(wallet) = signers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(signer) {
+        return Ok(wallet.clone().with_chain_id(chain_id));
+    }
+
+    let wallet = match signer {
+        SignerConfig::Env { private_key_env } => {
+            let private_key = env::var(private_key_env).map_err(|_| format!("environment variable {} is not set", private_key_env))?;
+            LocalWallet::from_str(&private_key).map_err(|e| format!("{} does not hold a valid private key: {}", private_key_env, e))?
+        },
+        SignerConfig::Keystore { path, password_env } => {
+            let password = match env::var(password_env) {
+                Ok(password) => password,
+                Err(_) => prompt_password(&format!("Password for keystore {}: ", path))
+                    .map_err(|e| format!("cannot read the password of keystore {}, set {}: {}", path, password_env, e))?,
+            };
+            LocalWallet::decrypt_keystore(path, password).map_err(|e| format!("cannot decrypt keystore {}: {}", path, e))?
+        },
+    };
+    signers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(signer.clone(), wallet.clone());
+    Ok(wallet.with_chain_id(chain_id))
+}
+
+// Loads the signers of the network up front and checks its walletAddress is the signer's.
+// Dry-run networks never sign anything and are skipped
+pub fn verify_signer(network_metadata: &NetworkMetadata) -> Result<(), Box<dyn Error>> {
+    if is_dry_run(network_metadata) {
+        log::info!("[{}] Dry run: signer not loaded", network_metadata.name);
+        return Ok(());
+    }
+
+    let wallet = load_signer(&network_metadata.signer, network_metadata.chain_id)
+        .map_err(|e| format!("[{}] signer: {}", network_metadata.name, e))?;
+    if wallet.address() != network_metadata.wallet_address {
+        return Err(format!(
+            "[{}] walletAddress {:?} does not match the signer's address {:?}",
+            network_metadata.name, network_metadata.wallet_address, wallet.address(),
+        ).into());
+    }
+
+    if let Some(identity) = &network_metadata.private_submission.identity {
+        let identity = load_signer(identity, network_metadata.chain_id)
+            .map_err(|e| format!("[{}] privateSubmission.identity: {}", network_metadata.name, e))?;
+        log::info!("[{}] Bundle relays identify the bot as {:?}", network_metadata.name, identity.address());
+    }
+    log::info!("[{}] Signing as {:?}", network_metadata.name, wallet.address());
+    Ok(())
+}
+
+// Reads a line from the terminal with echo turned off
+fn prompt_password(prompt: &str) -> Result<String, Box<dyn Error>> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return Err("no terminal to ask for it".into());
+    }
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    let echo_off = Command::new("stty").arg("-echo").status().is_ok_and(|status| status.success());
+    let mut password = String::new();
+    let read = stdin.lock().read_line(&mut password);
+    if echo_off {
+        let _ = Command::new("stty").arg("echo").status();
+        eprintln!();
+    }
+    read?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
@@ -1,78 +1,237 @@
-use std::{sync::Arc, error::Error};
+use std::{sync::Arc, error::Error, time::Duration};
 
-use ethers::{providers::{Provider, Http, Middleware}, signers::LocalWallet, types::{H256, TransactionRequest}, middleware::SignerMiddleware};
+use chrono::Utc;
+use ethers::{
+    providers::{Provider, Http, Middleware},
+    signers::{LocalWallet, Signer},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionReceipt, H256, U256},
+    middleware::SignerMiddleware,
+    utils::parse_ether,
+};
+use tokio::time::{sleep, Instant};
 
-use crate::models::config_models::NetworkMetadata;
+use crate::models::{config_models::{NetworkMetadata, GasPolicy}, transaction_models::{TxReplacement, ReplacementKind}};
 
-use super::client::create_flashbot_client;
+use super::nonce_manager::nonce_manager;
+
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const NONCE_USED_GRACE: Duration = Duration::from_secs(10); // Receipts can be served a little after the nonce moved
+const MIN_FEE_BUMP_PERCENTAGE: f64 = 10.0; // Nodes reject replacements paying less than 10% more
+const CANCEL_GAS: u64 = 21_000; // Plain transfer
+
+// A transaction that made it on chain, possibly after replacements
+pub struct SentTransaction {
+    pub receipt: TransactionReceipt,
+    pub replacements: Vec<TxReplacement>,
+    pub canceled: bool, // The cancellation was mined instead of the transaction
+}
 
 pub async fn send_tx(
+    network_metadata: &NetworkMetadata,
     client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
-    tx_request: TransactionRequest,
-) -> Result<H256, Box<dyn Error>> {
-    // Attempt to send the transaction
-    // let pending_tx = match client.send_transaction(tx_request, None).await {
-    //     Ok(tx) => tx,
-    //     Err(e) => return Err(Box::new(e) as Box<dyn Error>),
-    // };
-
+    mut tx: TypedTransaction,
+) -> Result<SentTransaction, Box<dyn Error>> {
+    // Take the next nonce of the wallet, other trades may be sending from it at the same time
+    let nonces = nonce_manager(client.signer().chain_id(), client.address());
+    let nonce = nonces.reserve(client.as_ref()).await?;
+    tx.set_nonce(nonce);
 
-    let pending_tx = match client.send_transaction(tx_request, None).await {
+    let tx_hash = match client.send_transaction(tx.clone(), None).await {
         Ok(tx) => {
-            println!("************** Transaction Status: {}:", tx.tx_hash());
-            tx
+            log::debug!("[{}] Transaction {:?} sent at nonce {}", network_metadata.name, tx.tx_hash(), nonce);
+            tx.tx_hash()
         }
         Err(e) => {
-            println!("************** Error waiting for Tansaction to be mined: {:?}", e);
+            log::warn!("[{}] Failed to send the transaction at nonce {}: {:?}", network_metadata.name, nonce, e);
+            // Never broadcast, the nonce is still free
+            nonces.release(nonce).await;
             return Err(Box::new(e) as Box<dyn Error>);
         }
     };
 
-    // Await the transaction to be mined
-    let receipt = match pending_tx.await {
-        Ok(receipt) => {
-            println!("Transaction mined. Status");
-            receipt
+    watch_nonce(network_metadata, client, nonce, tx, vec![tx_hash], Vec::new()).await
+}
+
+// Frees the nonce of a transaction handed to private endpoints that may still include it, with a cancellation
+// sent to the public mempool. Whichever of the two gets mined is returned
+pub async fn cancel_private(
+    network_metadata: &NetworkMetadata,
+    client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
+    tx: TypedTransaction,
+    tx_hash: H256,
+) -> Result<SentTransaction, Box<dyn Error>> {
+    let policy = &network_metadata.transaction_policy;
+    let nonce = tx.nonce().copied().ok_or("Transaction has no nonce")?;
+    let cancel = cancellation(&bumped(&tx, policy.fee_bump_percentage), client.address());
+
+    let mut sent_hashes = vec![tx_hash];
+    let mut replacements = Vec::new();
+    match client.send_transaction(cancel.clone(), None).await {
+        Ok(pending_cancel) => {
+            log::warn!(
+                "[{}] Private transaction {:?} not included, sent cancellation {:?} at nonce {}",
+                network_metadata.name, tx_hash, pending_cancel.tx_hash(), nonce,
+            );
+            sent_hashes.push(pending_cancel.tx_hash());
+            replacements.push(replacement_record(ReplacementKind::Cancel, nonce, tx_hash, pending_cancel.tx_hash(), &cancel));
+        },
+        // Typically "nonce too low" when the private transaction got mined meanwhile, the watch finds out
+        Err(e) => log::warn!("[{}] Failed to cancel private transaction {:?}: {}", network_metadata.name, tx_hash, e),
+    }
+    watch_nonce(network_metadata, client, nonce, cancel, sent_hashes, replacements).await
+}
+
+// Waits for one of the transactions sent at the nonce to be mined, and replaces the last one
+// with higher fees, then with a cancellation, while none is. `tx` is the last one sent
+async fn watch_nonce(
+    network_metadata: &NetworkMetadata,
+    client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
+    nonce: U256,
+    mut tx: TypedTransaction,
+    mut sent_hashes: Vec<H256>,
+    mut replacements: Vec<TxReplacement>,
+) -> Result<SentTransaction, Box<dyn Error>> {
+    let policy = &network_metadata.transaction_policy;
+    let inclusion_timeout = Duration::from_secs(policy.inclusion_timeout_secs);
+    let nonces = nonce_manager(client.signer().chain_id(), client.address());
+    let mut canceled = replacements.last().is_some_and(|replacement| replacement.kind == ReplacementKind::Cancel);
+
+    loop {
+        let mut receipt = wait_for_receipt(client.as_ref(), &sent_hashes, inclusion_timeout).await;
+        if receipt.is_none() {
+            match client.get_transaction_count(client.address(), None).await {
+                // The nonce was used, by one of the hashes sent or by a transaction from elsewhere
+                Ok(count) if count > nonce => {
+                    receipt = wait_for_receipt(client.as_ref(), &sent_hashes, NONCE_USED_GRACE).await;
+                    if receipt.is_none() {
+                        nonces.resync(client.as_ref()).await?;
+                        return Err(format!("Nonce {} was used by another transaction", nonce).into());
+                    }
+                },
+                Ok(_) => (),
+                // The transaction may still be pending, keep watching it
+                Err(e) => {
+                    log::warn!("[{}] Failed to fetch the nonce of the wallet, still waiting for nonce {}: {}", network_metadata.name, nonce, e);
+                    continue;
+                },
+            }
         }
-        Err(e) => {
-            println!("Error waiting for transaction to be mined: {:?}", e);
-            return Err(Box::new(e) as Box<dyn Error>);
+        if let Some(receipt) = receipt {
+            log::debug!("[{}] Transaction {:?} mined at nonce {}, status {:?}", network_metadata.name, receipt.transaction_hash, nonce, receipt.status);
+            nonces.confirm(nonce).await;
+            // Only the cancellation is a self-transfer, the swap went through when any other hash was mined
+            let canceled = canceled && replacements.last().is_some_and(|replacement| replacement.tx_hash == receipt.transaction_hash);
+            return Ok(SentTransaction { receipt, replacements, canceled });
         }
-    };
-    // Ensure the receipt is available
-    let receipt = receipt.ok_or_else(|| {
-        Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Transaction receipt not found")) as Box<dyn Error>
-    })?;
 
-    // Extract the transaction hash
-    let transaction_hash = receipt.transaction_hash;
+        let fee_bumps = replacements.iter().filter(|replacement| replacement.kind == ReplacementKind::FeeBump).count() as u32;
+        let kind = if canceled {
+            // The cancellation itself is stuck, keep waiting for it
+            continue;
+        } else if fee_bumps < policy.max_replacements && within_fee_cap(&network_metadata.gas_policy, &bumped(&tx, policy.fee_bump_percentage)) {
+            ReplacementKind::FeeBump
+        } else if policy.cancel_when_exhausted {
+            ReplacementKind::Cancel
+        } else {
+            // Nothing more to send, but the transaction still holds the nonce and may yet be mined
+            log::warn!("[{}] Transaction at nonce {} not included after {} replacements, still waiting for it", network_metadata.name, nonce, fee_bumps);
+            continue;
+        };
 
-    // Return the transaction hash
-    Ok(transaction_hash)
+        let replacement = match kind {
+            ReplacementKind::FeeBump => bumped(&tx, policy.fee_bump_percentage),
+            ReplacementKind::Cancel => cancellation(&bumped(&tx, policy.fee_bump_percentage), client.address()),
+        };
+        let replaced_tx_hash = *sent_hashes.last().unwrap_or(&H256::zero());
+        match client.send_transaction(replacement.clone(), None).await {
+            Ok(pending_replacement) => {
+                log::warn!(
+                    "[{}] Transaction {:?} not included after {}s, sent {:?} replacement {:?} at nonce {}",
+                    network_metadata.name, replaced_tx_hash, policy.inclusion_timeout_secs, kind, pending_replacement.tx_hash(), nonce,
+                );
+                sent_hashes.push(pending_replacement.tx_hash());
+                replacements.push(replacement_record(kind, nonce, replaced_tx_hash, pending_replacement.tx_hash(), &replacement));
+                canceled = kind == ReplacementKind::Cancel;
+                tx = replacement;
+            },
+            // Typically "replacement transaction underpriced" or the original got mined meanwhile, check again
+            Err(e) => log::warn!("[{}] Failed to replace transaction {:?}: {}", network_metadata.name, replaced_tx_hash, e),
+        }
+    }
 }
 
-pub async fn send_tx_flashbots(
-    network_metadata: &NetworkMetadata,
-    tx_request: TransactionRequest,
-) -> Result<H256, Box<dyn Error>> {
-
-    // Create a Flashbots client
-    let flashbots_client = create_flashbot_client(network_metadata.rpc_url.as_str()).await?;
-    
-    // Send the transaction using the Flashbots middleware
-    let pending_tx = flashbots_client.send_transaction(tx_request, None).await?;
-
-    // Await the transaction to be mined and get the receipt
-    let receipt = pending_tx
-        .await?
-        .ok_or_else(|| Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Transaction not included",
-        )))?;
-
-    // Extract the transaction hash
-    let tx_hash: H256 = receipt.transaction_hash;
-
-    // Return the transaction hash
-    Ok::<H256, Box<dyn Error>>(tx_hash)
-}
\ No newline at end of file
+fn replacement_record(kind: ReplacementKind, nonce: U256, replaced_tx_hash: H256, tx_hash: H256, replacement: &TypedTransaction) -> TxReplacement {
+    let (max_fee_per_gas, max_priority_fee_per_gas) = fees_of(replacement);
+    TxReplacement {
+        kind,
+        nonce,
+        replaced_tx_hash,
+        tx_hash,
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+        sent_at: Utc::now().timestamp() as u64,
+    }
+}
+
+// Polls for a receipt of any of the hashes until the timeout, None when none of them was mined.
+// A failed poll is only logged, the transaction may still be pending
+async fn wait_for_receipt<M: Middleware>(client: &M, hashes: &[H256], timeout: Duration) -> Option<TransactionReceipt> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        for hash in hashes.iter().rev() {
+            match client.get_transaction_receipt(*hash).await {
+                Ok(Some(receipt)) => return Some(receipt),
+                Ok(None) => (),
+                Err(e) => log::warn!("Failed to fetch the receipt of {:?}: {}", hash, e),
+            }
+        }
+        if Instant::now() >= deadline {
+            return None;
+        }
+        sleep(RECEIPT_POLL_INTERVAL).await;
+    }
+}
+
+// Same transaction with every fee raised by the percentage, nodes only accept a replacement that pays at least 10% more
+fn bumped(tx: &TypedTransaction, fee_bump_percentage: f64) -> TypedTransaction {
+    let bump_bps = U256::from((fee_bump_percentage.max(MIN_FEE_BUMP_PERCENTAGE) * 100.0).ceil() as u64);
+    let bump = |fee: U256| fee + (fee * bump_bps / 10_000).max(U256::one());
+
+    let mut replacement = tx.clone();
+    match &mut replacement {
+        TypedTransaction::Eip1559(inner) => {
+            inner.max_fee_per_gas = inner.max_fee_per_gas.map(bump);
+            inner.max_priority_fee_per_gas = inner.max_priority_fee_per_gas.map(bump);
+        },
+        TypedTransaction::Legacy(inner) => inner.gas_price = inner.gas_price.map(bump),
+        TypedTransaction::Eip2930(inner) => inner.tx.gas_price = inner.tx.gas_price.map(bump),
+    }
+    replacement
+}
+
+// Zero-value transfer to the wallet itself at the same nonce and fees
+fn cancellation(tx: &TypedTransaction, wallet: Address) -> TypedTransaction {
+    let mut cancel = tx.clone();
+    cancel.set_to(wallet);
+    cancel.set_value(U256::zero());
+    cancel.set_data(Bytes::new());
+    cancel.set_gas(CANCEL_GAS);
+    cancel
+}
+
+fn fees_of(tx: &TypedTransaction) -> (U256, Option<U256>) {
+    match tx {
+        TypedTransaction::Eip1559(inner) => (inner.max_fee_per_gas.unwrap_or_default(), inner.max_priority_fee_per_gas),
+        _ => (tx.gas_price().unwrap_or_default(), None),
+    }
+}
+
+// Whether the transaction stays within the network's per-trade gas cap
+fn within_fee_cap(gas_policy: &GasPolicy, tx: &TypedTransaction) -> bool {
+    let Some(max_fee_per_trade) = gas_policy.max_fee_per_trade else {
+        return true;
+    };
+    let (max_fee_per_gas, _) = fees_of(tx);
+    let max_cost = max_fee_per_gas * tx.gas().copied().unwrap_or_default();
+    parse_ether(max_fee_per_trade).map(|cap| max_cost <= cap).unwrap_or(false)
+}
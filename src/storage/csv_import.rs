@@ -0,0 +1,47 @@
+use std::{error::Error, fs, path::Path};
+
+use csv::{ReaderBuilder, Trim};
+
+use crate::models::processed_trade::ProcessedTrade;
+
+use super::TradeStore;
+
+// Ledger file written by the bot before trades moved to SQLite
+pub const LEGACY_CSV_PATH: &str = "data.csv";
+
+// Loads every trade of a legacy CSV ledger into the store in a single transaction
+pub fn import_csv<P: AsRef<Path>>(path: P, store: &dyn TradeStore) -> Result<usize, Box<dyn Error>> {
+    let mut rdr = ReaderBuilder::new()
+        .trim(Trim::All)
+        .flexible(true) // Rows written before a column was added are shorter
+        .from_path(path)?;
+    let headers = rdr.headers()?.clone();
+
+    let mut trades: Vec<ProcessedTrade> = Vec::new();
+    for result in rdr.records() {
+        let record = result?;
+        // The old appender wrote the header again every time it created the file
+        if record == headers {
+            continue;
+        }
+        let mut trade: ProcessedTrade = record.deserialize(Some(&headers))?;
+        trade.id = None;
+        trades.push(trade);
+    }
+
+    store.import(&trades)
+}
+
+// Imports the legacy ledger once, then renames it so the next start does not import it again
+pub fn import_legacy_csv(store: &dyn TradeStore) -> Result<(), Box<dyn Error>> {
+    let path = Path::new(LEGACY_CSV_PATH);
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let imported = import_csv(path, store)?;
+    let imported_path = format!("{}.imported", LEGACY_CSV_PATH);
+    fs::rename(path, &imported_path)?;
+    log::info!("Imported {} trades from {} (moved to {})", imported, LEGACY_CSV_PATH, imported_path);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+pub mod sqlite_store;
+pub mod csv_import;
+
+use std::{sync::{Arc, OnceLock}, error::Error};
+
+use crate::models::{processed_trade::{ProcessedTrade, TradeStatus}, transaction_models::{TokenApproval, TxReplacement}};
+
+pub use sqlite_store::SqliteTradeStore;
+
+pub const TRADES_DB_PATH: &str = "trades.db";
+
+static TRADE_STORE: OnceLock<Arc<dyn TradeStore>> = OnceLock::new();
+
+// Filters for listing trades, unset fields match everything
+#[derive(Debug, Clone, Default)]
+pub struct TradeQuery {
+    pub status: Option<TradeStatus>,
+    pub network_chain_id: Option<String>,
+    pub updated_from: Option<u64>, // Unix seconds, inclusive
+    pub updated_to: Option<u64>,   // Unix seconds, inclusive
+}
+
+pub trait TradeStore: Send + Sync {
+    // Inserts a new trade and returns its id
+    fn insert(&self, trade: &ProcessedTrade) -> Result<i64, Box<dyn Error>>;
+
+    // Overwrites the stored trade with the same id
+    fn update(&self, trade: &ProcessedTrade) -> Result<(), Box<dyn Error>>;
+
+    fn find(&self, query: &TradeQuery) -> Result<Vec<ProcessedTrade>, Box<dyn Error>>;
+
+    // Appends transactions resubmitted while the trade waited for inclusion
+    fn record_replacements(&self, trade_id: i64, replacements: &[TxReplacement]) -> Result<(), Box<dyn Error>>;
+
+    // Appends an approval sent before selling the trade's tokens
+    fn record_approval(&self, trade_id: i64, approval: &TokenApproval) -> Result<(), Box<dyn Error>>;
+
+    // Inserts all the trades or none of them
+    fn import(&self, trades: &[ProcessedTrade]) -> Result<usize, Box<dyn Error>>;
+}
+
+pub fn init_trade_store(store: Arc<dyn TradeStore>) -> Result<(), Box<dyn Error>> {
+    TRADE_STORE.set(store).map_err(|_| "Trade store already initialized")?;
+    Ok(())
+}
+
+pub fn trade_store() -> Result<Arc<dyn TradeStore>, Box<dyn Error>> {
+    TRADE_STORE.get().cloned().ok_or_else(|| "Trade store not initialized".into())
+}
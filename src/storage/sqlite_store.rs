@@ -0,0 +1,372 @@
+use std::{error::Error, path::Path, sync::Mutex};
+
+use rusqlite::{params, Connection, Row, ToSql};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::models::{processed_trade::ProcessedTrade, transaction_models::{TokenApproval, TxReplacement}};
+
+use super::{TradeQuery, TradeStore};
+
+// Applied in order, the index of the last applied migration + 1 is kept in PRAGMA user_version
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE trades (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        network_chain_id TEXT NOT NULL,
+        network_name TEXT NOT NULL,
+        exchange_name TEXT NOT NULL,
+        pair_address TEXT NOT NULL,
+        token_address TEXT NOT NULL,
+        base_token_address TEXT NOT NULL,
+        last_update INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        substatus TEXT,
+        security_score REAL,
+        amount_bought REAL,
+        amount_sold REAL,
+        exchange_fee_paid REAL,
+        gas_fee_paid REAL,
+        profit_or_loss REAL,
+        multiplier REAL,
+        bot_wallet_balance REAL,
+        simulated INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX idx_trades_status ON trades(status);
+    CREATE INDEX idx_trades_network ON trades(network_chain_id);
+    CREATE INDEX idx_trades_last_update ON trades(last_update);",
+    "ALTER TABLE trades ADD COLUMN score_breakdown TEXT;",
+    "CREATE TABLE trade_replacements (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        trade_id INTEGER NOT NULL REFERENCES trades(id),
+        kind TEXT NOT NULL,
+        nonce TEXT NOT NULL,
+        replaced_tx_hash TEXT NOT NULL,
+        tx_hash TEXT NOT NULL,
+        max_fee_per_gas TEXT NOT NULL,
+        max_priority_fee_per_gas TEXT,
+        sent_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_trade_replacements_trade ON trade_replacements(trade_id);",
+    "CREATE TABLE trade_approvals (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        trade_id INTEGER NOT NULL REFERENCES trades(id),
+        token_address TEXT NOT NULL,
+        spender TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        tx_hash TEXT NOT NULL,
+        gas_fee TEXT NOT NULL,
+        approved_at INTEGER NOT NULL
+    );
+    CREATE INDEX idx_trade_approvals_trade ON trade_approvals(trade_id);",
+    "ALTER TABLE trades ADD COLUMN fee_tier INTEGER;",
+    "ALTER TABLE trades ADD COLUMN opened_at INTEGER;",
+];
+
+const TRADE_COLUMNS: &str = "id, network_chain_id, network_name, exchange_name, pair_address, token_address, base_token_address, \
+    last_update, status, substatus, security_score, amount_bought, amount_sold, exchange_fee_paid, gas_fee_paid, \
+    profit_or_loss, multiplier, bot_wallet_balance, simulated, score_breakdown, fee_tier, opened_at";
+
+pub struct SqliteTradeStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteTradeStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let mut conn = Connection::open(path)?;
+        // Several tasks write concurrently, wait for the lock instead of failing
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        migrate(&mut conn)?;
+        Ok(SqliteTradeStore { conn: Mutex::new(conn) })
+    }
+
+    fn connection(&self) -> Result<std::sync::MutexGuard<'_, Connection>, Box<dyn Error>> {
+        self.conn.lock().map_err(|_| "Trade store connection poisoned".into())
+    }
+}
+
+impl TradeStore for SqliteTradeStore {
+    fn insert(&self, trade: &ProcessedTrade) -> Result<i64, Box<dyn Error>> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let id = insert_trade(&tx, trade)?;
+        tx.commit()?;
+        Ok(id)
+    }
+
+    fn update(&self, trade: &ProcessedTrade) -> Result<(), Box<dyn Error>> {
+        let id = trade.id.ok_or("Cannot update a trade that was never stored")?;
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        let updated = tx.execute(
+            "UPDATE trades SET network_chain_id = ?2, network_name = ?3, exchange_name = ?4, pair_address = ?5,
+                token_address = ?6, base_token_address = ?7, last_update = ?8, status = ?9, substatus = ?10,
+                security_score = ?11, amount_bought = ?12, amount_sold = ?13, exchange_fee_paid = ?14, gas_fee_paid = ?15,
+                profit_or_loss = ?16, multiplier = ?17, bot_wallet_balance = ?18, simulated = ?19, score_breakdown = ?20,
+                fee_tier = ?21, opened_at = ?22
+            WHERE id = ?1",
+            params![
+                id,
+                trade.network_chain_id,
+                trade.network_name,
+                trade.exchange_name,
+                format!("{:#x}", trade.pair_address),
+                format!("{:#x}", trade.token_address),
+                format!("{:#x}", trade.base_token_address),
+                unix_seconds(&trade.last_update),
+                to_text(&trade.status)?,
+                trade.substatus.as_ref().map(to_text).transpose()?,
+                trade.security_score,
+                trade.amount_bought,
+                trade.amount_sold,
+                trade.exchange_fee_paid,
+                trade.gas_fee_paid,
+                trade.profit_or_loss,
+                trade.multiplier,
+                trade.bot_wallet_balance,
+                trade.simulated,
+                trade.score_breakdown.as_ref().map(serde_json::to_string).transpose()?,
+                trade.fee_tier,
+                trade.opened_at.map(|opened_at| opened_at as i64),
+            ],
+        )?;
+        if updated == 0 {
+            return Err(format!("Trade {} not found", id).into());
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn find(&self, query: &TradeQuery) -> Result<Vec<ProcessedTrade>, Box<dyn Error>> {
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+
+        if let Some(status) = &query.status {
+            conditions.push("status = ?");
+            values.push(Box::new(to_text(status)?));
+        }
+        if let Some(network_chain_id) = &query.network_chain_id {
+            conditions.push("network_chain_id = ?");
+            values.push(Box::new(network_chain_id.clone()));
+        }
+        if let Some(updated_from) = query.updated_from {
+            conditions.push("last_update >= ?");
+            values.push(Box::new(updated_from as i64));
+        }
+        if let Some(updated_to) = query.updated_to {
+            conditions.push("last_update <= ?");
+            values.push(Box::new(updated_to as i64));
+        }
+
+        let mut sql = format!("SELECT {} FROM trades", TRADE_COLUMNS);
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(" ORDER BY last_update, id");
+
+        let conn = self.connection()?;
+        let mut statement = conn.prepare(&sql)?;
+        let rows = statement.query_map(rusqlite::params_from_iter(values.iter()), read_row)?;
+
+        let mut trades = Vec::new();
+        for row in rows {
+            trades.push(into_trade(row?)?);
+        }
+        Ok(trades)
+    }
+
+    fn record_replacements(&self, trade_id: i64, replacements: &[TxReplacement]) -> Result<(), Box<dyn Error>> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        for replacement in replacements {
+            // Wei amounts can exceed SQLite integers, they are stored as decimal text
+            tx.execute(
+                "INSERT INTO trade_replacements (trade_id, kind, nonce, replaced_tx_hash, tx_hash, max_fee_per_gas, max_priority_fee_per_gas, sent_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    trade_id,
+                    to_text(&replacement.kind)?,
+                    replacement.nonce.to_string(),
+                    format!("{:#x}", replacement.replaced_tx_hash),
+                    format!("{:#x}", replacement.tx_hash),
+                    replacement.max_fee_per_gas.to_string(),
+                    replacement.max_priority_fee_per_gas.map(|fee| fee.to_string()),
+                    replacement.sent_at as i64,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn record_approval(&self, trade_id: i64, approval: &TokenApproval) -> Result<(), Box<dyn Error>> {
+        self.connection()?.execute(
+            "INSERT INTO trade_approvals (trade_id, token_address, spender, amount, tx_hash, gas_fee, approved_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                trade_id,
+                format!("{:#x}", approval.token),
+                format!("{:#x}", approval.spender),
+                approval.amount.to_string(),
+                format!("{:#x}", approval.tx_hash),
+                approval.gas_fee.to_string(),
+                approval.approved_at as i64,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn import(&self, trades: &[ProcessedTrade]) -> Result<usize, Box<dyn Error>> {
+        let mut conn = self.connection()?;
+        let tx = conn.transaction()?;
+        for trade in trades {
+            insert_trade(&tx, trade)?;
+        }
+        tx.commit()?;
+        Ok(trades.len())
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+    let applied: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if applied > MIGRATIONS.len() {
+        return Err(format!("Database schema version {} is newer than this build supports", applied).into());
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+        log::info!("Applied trade store migration {}", index + 1);
+    }
+    Ok(())
+}
+
+fn insert_trade(conn: &Connection, trade: &ProcessedTrade) -> Result<i64, Box<dyn Error>> {
+    conn.execute(
+        "INSERT INTO trades (network_chain_id, network_name, exchange_name, pair_address, token_address, base_token_address,
+            last_update, status, substatus, security_score, amount_bought, amount_sold, exchange_fee_paid, gas_fee_paid,
+            profit_or_loss, multiplier, bot_wallet_balance, simulated, score_breakdown, fee_tier, opened_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
+        params![
+            trade.network_chain_id,
+            trade.network_name,
+            trade.exchange_name,
+            format!("{:#x}", trade.pair_address),
+            format!("{:#x}", trade.token_address),
+            format!("{:#x}", trade.base_token_address),
+            unix_seconds(&trade.last_update),
+            to_text(&trade.status)?,
+            trade.substatus.as_ref().map(to_text).transpose()?,
+            trade.security_score,
+            trade.amount_bought,
+            trade.amount_sold,
+            trade.exchange_fee_paid,
+            trade.gas_fee_paid,
+            trade.profit_or_loss,
+            trade.multiplier,
+            trade.bot_wallet_balance,
+            trade.simulated,
+            trade.score_breakdown.as_ref().map(serde_json::to_string).transpose()?,
+            trade.fee_tier,
+            trade.opened_at.map(|opened_at| opened_at as i64),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+// Raw column values, converted outside of rusqlite so parse errors keep their message
+struct TradeRow {
+    id: i64,
+    network_chain_id: String,
+    network_name: String,
+    exchange_name: String,
+    pair_address: String,
+    token_address: String,
+    base_token_address: String,
+    last_update: i64,
+    status: String,
+    substatus: Option<String>,
+    security_score: Option<f64>,
+    amount_bought: Option<f64>,
+    amount_sold: Option<f64>,
+    exchange_fee_paid: Option<f64>,
+    gas_fee_paid: Option<f64>,
+    profit_or_loss: Option<f64>,
+    multiplier: Option<f64>,
+    bot_wallet_balance: Option<f64>,
+    simulated: bool,
+    score_breakdown: Option<String>,
+    fee_tier: Option<u32>,
+    opened_at: Option<i64>,
+}
+
+fn read_row(row: &Row) -> rusqlite::Result<TradeRow> {
+    Ok(TradeRow {
+        id: row.get(0)?,
+        network_chain_id: row.get(1)?,
+        network_name: row.get(2)?,
+        exchange_name: row.get(3)?,
+        pair_address: row.get(4)?,
+        token_address: row.get(5)?,
+        base_token_address: row.get(6)?,
+        last_update: row.get(7)?,
+        status: row.get(8)?,
+        substatus: row.get(9)?,
+        security_score: row.get(10)?,
+        amount_bought: row.get(11)?,
+        amount_sold: row.get(12)?,
+        exchange_fee_paid: row.get(13)?,
+        gas_fee_paid: row.get(14)?,
+        profit_or_loss: row.get(15)?,
+        multiplier: row.get(16)?,
+        bot_wallet_balance: row.get(17)?,
+        simulated: row.get(18)?,
+        score_breakdown: row.get(19)?,
+        fee_tier: row.get(20)?,
+        opened_at: row.get(21)?,
+    })
+}
+
+fn into_trade(row: TradeRow) -> Result<ProcessedTrade, Box<dyn Error>> {
+    Ok(ProcessedTrade {
+        id: Some(row.id),
+        network_chain_id: row.network_chain_id,
+        network_name: row.network_name,
+        exchange_name: row.exchange_name,
+        pair_address: row.pair_address.parse()?,
+        token_address: row.token_address.parse()?,
+        base_token_address: row.base_token_address.parse()?,
+        last_update: row.last_update.to_string(),
+        status: from_text(row.status)?,
+        substatus: row.substatus.map(from_text).transpose()?,
+        security_score: row.security_score,
+        amount_bought: row.amount_bought,
+        amount_sold: row.amount_sold,
+        exchange_fee_paid: row.exchange_fee_paid,
+        gas_fee_paid: row.gas_fee_paid,
+        profit_or_loss: row.profit_or_loss,
+        multiplier: row.multiplier,
+        bot_wallet_balance: row.bot_wallet_balance,
+        simulated: row.simulated,
+        score_breakdown: row.score_breakdown.as_deref().map(serde_json::from_str).transpose()?,
+        fee_tier: row.fee_tier,
+        opened_at: row.opened_at.map(|opened_at| opened_at as u64),
+    })
+}
+
+// Enums are stored under their serde name, the same text the CSV ledger used
+fn to_text<T: Serialize>(value: &T) -> Result<String, Box<dyn Error>> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(text) => Ok(text),
+        other => Err(format!("Cannot store {} as text", other).into()),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(text: String) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_value(serde_json::Value::String(text))?)
+}
+
+fn unix_seconds(last_update: &str) -> i64 {
+    last_update.parse().unwrap_or_default()
+}
@@ -0,0 +1,110 @@
+use std::{collections::HashMap, error::Error, sync::{Arc, Mutex, OnceLock}};
+
+use chrono::Utc;
+use ethers::{
+    abi::AbiEncode,
+    providers::{Provider, Http, Middleware},
+    types::{Address, NameOrAddress, TransactionRequest, U256, U64},
+};
+
+use crate::{
+    bindings::erc20::{ApproveCall, Erc20},
+    models::{config_models::{NetworkMetadata, ApprovalPolicy}, transaction_models::TokenApproval},
+    network::{client::create_client_arc, gas::{priced_transaction, GasStrategy, Urgency}, transaction::send_tx},
+};
+
+use super::executor::is_dry_run;
+
+// Known allowances per (chain id, wallet, token, spender), saves a call before every sell
+type Allowances = Mutex<HashMap<(u64, Address, Address, Address), U256>>;
+static ALLOWANCES: OnceLock<Allowances> = OnceLock::new();
+
+// Makes sure the spender may move `amount` of the wallet's tokens, sends an approval first when it may not
+pub async fn ensure_allowance(
+    network_metadata: &NetworkMetadata,
+    token: Address,
+    spender: Address,
+    amount: U256,
+) -> Result<Option<TokenApproval>, Box<dyn Error>> {
+    let chain_id = network_metadata.chain_id;
+    let wallet = network_metadata.wallet_address;
+    let key = (chain_id, wallet, token, spender);
+    if cached_allowance(key).is_some_and(|allowance| allowance >= amount) {
+        return Ok(None);
+    }
+
+    let provider = Arc::new(Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?);
+    let allowance = Erc20::new(token, provider).allowance(wallet, spender).call().await?;
+    cache_allowance(key, allowance);
+    if allowance >= amount {
+        return Ok(None);
+    }
+
+    // Dry-run sells are never sent, so nothing needs to be approved
+    if is_dry_run(network_metadata) {
+        log::info!("[{}] Dry run: skipped approving {:?} to spend {} of {:?}", network_metadata.name, spender, amount, token);
+        return Ok(None);
+    }
+
+    let approved_amount = match network_metadata.approval_policy {
+        ApprovalPolicy::Exact => amount,
+        ApprovalPolicy::Max => U256::MAX,
+    };
+    let mut tx_request = TransactionRequest {
+        chain_id: Some(U64::from(chain_id)),
+        from: Some(wallet),
+        to: Some(NameOrAddress::Address(token)),
+        data: Some(ApproveCall { spender, value: approved_amount }.encode().into()),
+        ..Default::default()
+    };
+
+    let client_arc = create_client_arc(network_metadata.rpc_url.as_str(), chain_id, &network_metadata.signer).await?;
+    let gas_strategy = GasStrategy::new(&network_metadata.gas_policy);
+    let estimated_gas = client_arc.estimate_gas(&tx_request.clone().into(), None).await?;
+    // The approval is the first step of an exit
+    let fees = gas_strategy.cap(gas_strategy.fees(client_arc.as_ref(), Urgency::Exit).await?, estimated_gas)?;
+    tx_request.gas = Some(estimated_gas);
+
+    let sent = send_tx(network_metadata, client_arc, priced_transaction(tx_request, fees)).await?;
+    let receipt = &sent.receipt;
+    if sent.canceled {
+        return Err(format!("Approval of {:?} for {:?} was not included in time and got canceled", token, spender).into());
+    }
+    if receipt.status != Some(U64::one()) {
+        return Err(format!("Approval of {:?} for {:?} reverted in {:?}", token, spender, receipt.transaction_hash).into());
+    }
+    cache_allowance(key, approved_amount);
+
+    log::info!(
+        "[{}] Approved {:?} to spend {} of {:?}. Transaction hash: {:?}",
+        network_metadata.name, spender, approved_amount, token, receipt.transaction_hash,
+    );
+    Ok(Some(TokenApproval {
+        token,
+        spender,
+        amount: approved_amount,
+        tx_hash: receipt.transaction_hash,
+        gas_fee: receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or(fees.expected_price()),
+        approved_at: Utc::now().timestamp() as u64,
+    }))
+}
+
+// Lowers the known allowance after the spender moved `amount`, maximum approvals are never spent down
+pub fn record_spent(network_metadata: &NetworkMetadata, token: Address, spender: Address, amount: U256) {
+    let key = (network_metadata.chain_id, network_metadata.wallet_address, token, spender);
+    if let Some(allowance) = cached_allowance(key).filter(|allowance| *allowance != U256::MAX) {
+        cache_allowance(key, allowance.saturating_sub(amount));
+    }
+}
+
+fn cached_allowance(key: (u64, Address, Address, Address)) -> Option<U256> {
+    let allowances = ALLOWANCES.get_or_init(|| Mutex::new(HashMap::new()));
+    let allowances = allowances.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    allowances.get(&key).copied()
+}
+
+fn cache_allowance(key: (u64, Address, Address, Address), allowance: U256) {
+    let allowances = ALLOWANCES.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut allowances = allowances.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    allowances.insert(key, allowance);
+}
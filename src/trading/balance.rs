@@ -1,5 +1,3 @@
-use std::sync::Arc;
-
 use ethers::{types::{H160, U256}, providers::{Provider, Http, Middleware}};
 
 use crate::{errors::SendableError, bindings::erc20::Erc20};
@@ -21,8 +19,7 @@ pub async fn get_token_balance(rpc_url: &str, wallet_address: H160, token_addres
 pub async fn get_native_balance(rpc_url: &str, wallet_address: H160) -> Result<U256, SendableError> {
     // Create a provider connected to the Ethereum network with a type annotation
     let provider: Provider<Http> = Provider::<Http>::try_from(rpc_url)
-        .map_err(|e| SendableError::from(format!("Failed to create provider: {}", e)))?
-        .into();
+        .map_err(|e| SendableError::from(format!("Failed to create provider: {}", e)))?;
 
     // Fetch the native token balance of the bot's wallet address
     provider
@@ -0,0 +1,186 @@
+use std::{sync::Arc, error::Error, env};
+
+use ethers::{
+    prelude::*,
+    providers::Middleware,
+};
+
+use crate::{
+    bindings::erc20::TransferFilter,
+    models::{config_models::NetworkMetadata, transaction_models::TxReplacement},
+    dex::{DexAdapter, Quote, SwapDirection, SwapRequest},
+    network::{client::create_client_arc, gas::{priced_transaction, GasStrategy, Urgency}, transaction::send_tx, private_submission::{send_private, BundleOutputCheck}},
+};
+
+// Overrides the dryRun flag of every network when set ("1", "true" or "yes" to enable)
+const DRY_RUN_ENV: &str = "DRY_RUN";
+const TX_BASE_GAS: u64 = 21_000; // Intrinsic cost of a transaction, quoted gas only covers the swap
+
+// Emitted by the wrapped native token when the router unwraps the output of a sale
+#[derive(Clone, Debug, EthEvent)]
+#[ethevent(name = "Withdrawal", abi = "Withdrawal(address,uint256)")]
+struct WithdrawalFilter {
+    #[ethevent(indexed)]
+    src: Address,
+    wad: U256,
+}
+
+// Outcome of a swap, real or simulated
+#[derive(Debug, Clone)]
+pub struct SwapExecution {
+    pub tx_hash: Option<H256>, // None when the swap was simulated
+    pub quote: Quote, // What the swap was sized from, amount_out is the expected output before slippage
+    pub amount_out: Option<U256>, // What the swap actually delivered, read from the receipt. None when simulated or canceled
+    pub gas_fee: U256, // Gas paid in wei, or the expected cost when simulated
+    pub simulated: bool,
+    pub replacements: Vec<TxReplacement>, // Fee bumps or cancellation sent while waiting for inclusion
+    pub canceled: bool, // The swap was canceled, only the cancellation's gas was paid
+}
+
+pub fn is_dry_run(network_metadata: &NetworkMetadata) -> bool {
+    match env::var(DRY_RUN_ENV) {
+        Ok(value) => matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"),
+        Err(_) => network_metadata.dry_run,
+    }
+}
+
+// Quotes a swap on the exchange
+pub async fn quote_swap(
+    adapter: &dyn DexAdapter,
+    network_metadata: &NetworkMetadata,
+    request: &SwapRequest,
+) -> Result<Quote, Box<dyn Error>> {
+    let provider = Arc::new(Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?);
+    let quote = adapter.quote(provider, request).await?;
+    log::info!(
+        "[{}] Quoted {} of {:?} for {} of {:?}, gas estimate {:?}, ticks crossed {:?}",
+        network_metadata.name, request.amount_in, request.token_in, quote.amount_out, request.token_out, quote.gas_estimate, quote.ticks_crossed,
+    );
+    Ok(quote)
+}
+
+// Builds the router call for the swap and sends it through the network's submission route,
+// or only simulates it when the network runs in dry-run mode
+pub async fn execute_swap(
+    adapter: &dyn DexAdapter,
+    network_metadata: &NetworkMetadata,
+    request: &SwapRequest,
+    quote: Quote,
+    urgency: Urgency,
+) -> Result<SwapExecution, Box<dyn Error>> {
+    let swap_call = adapter.build_swap(request)?;
+
+    // Create the TransactionRequest manually
+    let mut tx_request = TransactionRequest {
+        chain_id: Some(U64::from(network_metadata.chain_id)),
+        from: Some(network_metadata.wallet_address),
+        to: Some(NameOrAddress::Address(swap_call.to)),
+        gas: None,
+        gas_price: None, // Priced by the network's gas strategy
+        value: swap_call.value,
+        data: Some(swap_call.data),
+        // Nonce is reserved from the wallet's nonce manager when the transaction is sent
+        nonce: None,
+    };
+
+    let gas_strategy = GasStrategy::new(&network_metadata.gas_policy);
+
+    if is_dry_run(network_metadata) {
+        return simulate_swap(adapter, network_metadata, &gas_strategy, tx_request, quote, urgency).await;
+    }
+
+    let client_arc = create_client_arc(network_metadata.rpc_url.as_str(), network_metadata.chain_id, &network_metadata.signer).await?;
+
+    // Estimate the gas for the transaction and price it for the urgency of the swap
+    let estimated_gas = client_arc.estimate_gas(&tx_request.clone().into(), None).await?;
+    let fees = gas_strategy.cap(gas_strategy.fees(client_arc.as_ref(), urgency).await?, estimated_gas)?;
+    tx_request.gas = Some(estimated_gas);
+    let tx = priced_transaction(tx_request, fees);
+
+    // The network's private endpoints keep the swap out of the public mempool
+    let sent = if network_metadata.private_submission.endpoints.is_empty() {
+        // Send the transaction with the regular Ethereum client
+        send_tx(
+            network_metadata,
+            client_arc,
+            tx,
+        ).await?
+    } else {
+        // Dropped before submission when a bundle simulation does not return the minimum output
+        let decode = |output: &Bytes| adapter.decode_swap_output(request, output);
+        send_private(
+            network_metadata,
+            client_arc,
+            tx,
+            BundleOutputCheck { min_amount_out: request.amount_out_min, decode: &decode },
+        ).await?
+    };
+
+    let receipt = &sent.receipt;
+    if receipt.status != Some(U64::one()) {
+        return Err(format!("[{}] Swap reverted in {:?}", network_metadata.name, receipt.transaction_hash).into());
+    }
+    let gas_fee = receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or(fees.expected_price());
+    let amount_out = if sent.canceled { None } else { Some(received_amount(request, receipt)) };
+    Ok(SwapExecution {
+        tx_hash: Some(receipt.transaction_hash),
+        quote,
+        amount_out,
+        gas_fee,
+        simulated: false,
+        replacements: sent.replacements,
+        canceled: sent.canceled,
+    })
+}
+
+// Dry-run fill: nothing is signed or sent, the quote is taken as the fill and gas is estimated
+async fn simulate_swap(
+    adapter: &dyn DexAdapter,
+    network_metadata: &NetworkMetadata,
+    gas_strategy: &GasStrategy<'_>,
+    tx_request: TransactionRequest,
+    quote: Quote,
+    urgency: Urgency,
+) -> Result<SwapExecution, Box<dyn Error>> {
+    let provider = Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?;
+
+    // The estimate reverts when the wallet lacks the balance or the allowance, fall back to the quoted or a typical swap cost
+    let estimated_gas = provider
+        .estimate_gas(&tx_request.into(), None)
+        .await
+        .unwrap_or_else(|_| quote.gas_estimate.map_or_else(|| adapter.default_swap_gas(), |gas| gas + TX_BASE_GAS));
+    let fees = gas_strategy.cap(gas_strategy.fees(&provider, urgency).await?, estimated_gas)?;
+    let gas_fee = estimated_gas * fees.expected_price();
+
+    log::info!(
+        "[{}] Dry run: simulated swap with expected output {} and gas cost {} wei ({:?})",
+        network_metadata.name, quote.amount_out, gas_fee, fees,
+    );
+
+    Ok(SwapExecution {
+        tx_hash: None,
+        quote,
+        amount_out: None,
+        gas_fee,
+        simulated: true,
+        replacements: Vec::new(),
+        canceled: false,
+    })
+}
+
+// Output of a mined swap from its logs: the tokens transferred to the recipient, after any transfer tax.
+// Sales paid in the native token also count what the router unwrapped for the recipient
+fn received_amount(request: &SwapRequest, receipt: &TransactionReceipt) -> U256 {
+    let output_logs: Vec<Log> = receipt.logs.iter().filter(|log| log.address == request.token_out).cloned().collect();
+    let transferred = output_logs.iter()
+        .filter_map(|log| parse_log::<TransferFilter>(log.clone()).ok())
+        .filter(|transfer| transfer.to == request.recipient)
+        .fold(U256::zero(), |total, transfer| total + transfer.value);
+    let unwrapped = match request.direction {
+        SwapDirection::NativeForTokens => U256::zero(),
+        SwapDirection::TokensForNative => output_logs.iter()
+            .filter_map(|log| parse_log::<WithdrawalFilter>(log.clone()).ok())
+            .fold(U256::zero(), |total, withdrawal| total + withdrawal.wad),
+    };
+    transferred + unwrapped
+}
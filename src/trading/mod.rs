@@ -1,3 +1,8 @@
 pub mod balance;
 pub mod buy;
-pub mod sell;
\ No newline at end of file
+pub mod sell;
+pub mod executor;
+pub mod allowance;
+pub mod slippage;
+pub mod router;
+pub mod position_manager;
\ No newline at end of file
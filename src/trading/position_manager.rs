@@ -0,0 +1,260 @@
+use std::{sync::Arc, error::Error, future::pending, time::{Duration, Instant, SystemTime, UNIX_EPOCH}};
+
+use ethers::{
+    providers::{Provider, Http, Ws, Middleware, StreamExt},
+    types::{Address, Filter, U256},
+    utils::{format_ether, parse_ether},
+};
+use tokio::time::interval;
+
+use crate::{
+    analysis::security_checker::assess_token_security,
+    models::{config_models::{NetworkMetadata, ExchangeConfig, ExitRules}, processed_trade::ProcessedTrade, security_models::TransferTax},
+    dex::{adapter_for, DexAdapter, SwapDirection, SwapRequest},
+    utils::formatter::u256_to_f64,
+};
+
+use super::{
+    allowance::{ensure_allowance, record_spent},
+    balance::get_token_balance,
+    executor::{quote_swap, SwapExecution},
+    router::{base_hop, TokenRoute},
+    sell::{min_sell_output, sell_token},
+    slippage::apply_transfer_tax,
+};
+
+const PRICE_POLL_INTERVAL_SECS: u64 = 15; // Re-check the price even when the pool is quiet
+
+pub struct OpenPosition {
+    pub trade: ProcessedTrade,
+    pub token_address: Address,
+    pub route: TokenRoute,
+    pub amount_in_wei: U256, // What was paid for the position
+    pub amount_tokens: U256, // What the position holds
+    pub transfer_tax: TransferTax,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    TakeProfit,
+    StopLoss,
+    TrailingStop,
+    MaxHoldTime,
+}
+
+// Rebuilds a position stored by an earlier run, so it can be managed again
+pub async fn resume_position(network_metadata: &NetworkMetadata, exchange: &ExchangeConfig, trade: ProcessedTrade) -> Result<OpenPosition, Box<dyn Error>> {
+    let adapter = adapter_for(exchange)?;
+    let token_address = trade.token_address;
+    let via = base_hop(network_metadata, adapter.as_ref(), trade.base_token_address).await?;
+    let route = TokenRoute { pool: trade.pair_address, fee: trade.fee_tier, via };
+    let amount_in_wei = parse_ether(trade.amount_bought.unwrap_or_default().to_string().as_str())?;
+    if amount_in_wei.is_zero() {
+        return Err(format!("Trade #{} has no recorded buy", trade.id.unwrap_or_default()).into());
+    }
+
+    // The transfer tax is not stored, the token is assessed again
+    let transfer_tax = match assess_token_security(network_metadata, exchange, &token_address, &trade.base_token_address).await {
+        Ok(assessment) => assessment.transfer_tax,
+        Err(e) => {
+            log::warn!("[{} - {} - {}] Failed to assess the token again, assuming no transfer tax: {}", network_metadata.name, exchange.name, token_address, e);
+            TransferTax::default()
+        },
+    };
+
+    // A simulated fill never reached the wallet, quote what the amount paid would have bought
+    let amount_tokens = if trade.simulated {
+        let request = SwapRequest {
+            direction: SwapDirection::NativeForTokens,
+            token_in: network_metadata.wrapped_native_address,
+            token_out: token_address,
+            amount_in: amount_in_wei,
+            amount_out_min: U256::zero(),
+            recipient: network_metadata.wallet_address,
+            fee: route.fee,
+            fee_on_transfer: transfer_tax.is_taxed(),
+            via: route.via,
+        };
+        apply_transfer_tax(quote_swap(adapter.as_ref(), network_metadata, &request).await?.amount_out, transfer_tax.buy)
+    } else {
+        get_token_balance(&network_metadata.rpc_url, network_metadata.wallet_address, token_address).await?
+    };
+
+    Ok(OpenPosition { trade, token_address, route, amount_in_wei, amount_tokens, transfer_tax })
+}
+
+// Watches the pool of an open position and sells it once one of the network's exit rules fires
+pub async fn manage_position(network_metadata: NetworkMetadata, exchange: ExchangeConfig, mut position: OpenPosition) {
+    let network_name = &network_metadata.name;
+    let exchange_name = &exchange.name;
+    let token_address = position.token_address;
+
+    let adapter = match adapter_for(&exchange) {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            log::error!("[{} - {} - {}] Cannot manage position: {}", network_name, exchange_name, token_address, e);
+            return;
+        },
+    };
+    let provider = match Provider::<Http>::try_from(network_metadata.rpc_url.as_str()) {
+        Ok(provider) => Arc::new(provider),
+        Err(e) => {
+            log::error!("[{} - {} - {}] Cannot manage position: {}", network_name, exchange_name, token_address, e);
+            return;
+        },
+    };
+
+    // Swap events trigger an immediate price check, the poll interval covers quiet pools and a dead connection
+    let ws_provider = Provider::<Ws>::connect(&network_metadata.ws_url).await.ok();
+    let swap_filter = Filter::new().address(position.route.pool).topic0(adapter.swap_topic());
+    let mut swaps = match &ws_provider {
+        Some(ws_provider) => ws_provider.subscribe_logs(&swap_filter).await.ok(),
+        None => None,
+    };
+    if swaps.is_none() {
+        log::warn!("[{} - {} - {}] Swap subscription unavailable, falling back to polling.", network_name, exchange_name, token_address);
+    }
+
+    let rules = &network_metadata.exit_rules;
+    // Resumed positions keep the hold time of the earlier run. Trades stored before the buy time was
+    // recorded fall back to their last update, which is never earlier than the buy
+    let bought_at = position.trade.opened_at.or_else(|| position.trade.last_update.parse().ok());
+    let held_before = bought_at.map_or(Duration::ZERO, |bought_at| {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        Duration::from_secs(now.saturating_sub(bought_at))
+    });
+    let opened_at = Instant::now().checked_sub(held_before).unwrap_or_else(Instant::now);
+    let mut peak_multiplier: f64 = 1.0;
+    let mut ticker = interval(Duration::from_secs(PRICE_POLL_INTERVAL_SECS));
+    let mut close_attempts: u32 = 0;
+
+    log::info!("[{} - {} - {}] Managing position on pool {}", network_name, exchange_name, token_address, position.route.pool);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => (),
+            swap = async {
+                match swaps.as_mut() {
+                    Some(stream) => stream.next().await,
+                    None => pending().await,
+                }
+            } => {
+                if swap.is_none() {
+                    log::warn!("[{} - {} - {}] Swap stream closed, falling back to polling.", network_name, exchange_name, token_address);
+                    swaps = None;
+                }
+            },
+        }
+
+        let price = match native_price(adapter.as_ref(), provider.clone(), token_address, &position.route).await {
+            Ok(price) => price,
+            Err(e) => {
+                log::error!("[{} - {} - {}] Failed to fetch pool price: {}", network_name, exchange_name, token_address, e);
+                continue;
+            },
+        };
+
+        // Current value of the position against what was paid for it
+        let multiplier = u256_to_f64(position.amount_tokens) * price / u256_to_f64(position.amount_in_wei);
+        peak_multiplier = peak_multiplier.max(multiplier);
+
+        let Some(reason) = exit_reason(rules, multiplier, peak_multiplier, opened_at.elapsed()) else {
+            continue;
+        };
+
+        log::info!("[{} - {} - {}] Exit rule {:?} fired at {:.4}x (peak {:.4}x)", network_name, exchange_name, token_address, reason, multiplier, peak_multiplier);
+        let Err(e) = close_position(adapter.as_ref(), &network_metadata, &exchange, &mut position).await else {
+            return;
+        };
+        close_attempts += 1;
+        if close_attempts < rules.max_close_attempts {
+            log::error!(
+                "[{} - {} - {}] Failed to close position (attempt {} of {}), will retry: {}",
+                network_name, exchange_name, token_address, close_attempts, rules.max_close_attempts, e,
+            );
+            continue;
+        }
+
+        log::error!("[{} - {} - {}] Failed to close position after {} attempts, giving up: {}", network_name, exchange_name, token_address, close_attempts, e);
+        if let Err(e) = position.trade.close_failed() {
+            log::error!("[{} - {} - {}] Failed to record the failed close: {}", network_name, exchange_name, token_address, e);
+        }
+        return;
+    }
+}
+
+// Price of the token in the native token, through the base token's pool for two-hop routes
+async fn native_price(adapter: &dyn DexAdapter, provider: Arc<Provider<Http>>, token: Address, route: &TokenRoute) -> Result<f64, Box<dyn Error>> {
+    let price = adapter.spot_price(provider.clone(), route.pool, token).await?;
+    match route.via {
+        Some(via) => Ok(price * adapter.spot_price(provider, via.pool, via.token).await?),
+        None => Ok(price),
+    }
+}
+
+fn exit_reason(rules: &ExitRules, multiplier: f64, peak_multiplier: f64, held_for: Duration) -> Option<ExitReason> {
+    if multiplier >= rules.take_profit_multiplier {
+        return Some(ExitReason::TakeProfit);
+    }
+    if multiplier <= 1.0 - rules.stop_loss_percentage / 100.0 {
+        return Some(ExitReason::StopLoss);
+    }
+    if let Some(trailing_stop_percentage) = rules.trailing_stop_percentage {
+        if multiplier <= peak_multiplier * (1.0 - trailing_stop_percentage / 100.0) {
+            return Some(ExitReason::TrailingStop);
+        }
+    }
+    if held_for >= Duration::from_secs(rules.max_hold_seconds) {
+        return Some(ExitReason::MaxHoldTime);
+    }
+    None
+}
+
+async fn close_position(
+    adapter: &dyn DexAdapter,
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    position: &mut OpenPosition,
+) -> Result<(), Box<dyn Error>> {
+    let amount_out_min = min_sell_output(
+        adapter, exchange, network_metadata, position.token_address, position.amount_tokens, &position.route, position.transfer_tax,
+    ).await?;
+
+    // The router can only swap the tokens once it is allowed to spend them
+    let spender = adapter.router_address();
+    if let Some(approval) = ensure_allowance(network_metadata, position.token_address, spender, position.amount_tokens).await? {
+        position.trade.approved(&approval)?;
+    }
+
+    let execution = sell_token(
+        exchange.clone(),
+        network_metadata.clone(),
+        position.token_address,
+        position.amount_tokens,
+        amount_out_min,
+        position.route,
+        position.transfer_tax,
+    ).await?;
+    position.trade.record_replacements(&execution.replacements)?;
+    if execution.canceled {
+        return Err("Sell was not included in time and got canceled".into());
+    }
+    if !execution.simulated {
+        record_spent(network_metadata, position.token_address, spender, position.amount_tokens);
+    }
+    log::info!(
+        "[{} - {} - {}] Position closed. Transaction hash: {:?}, simulated: {}",
+        network_metadata.name, exchange.name, position.token_address, execution.tx_hash, execution.simulated,
+    );
+
+    record_sale(&mut position.trade, &execution)
+}
+
+// Closes the trade with the realized proceeds of the sale, simulated fills only have the quote
+pub fn record_sale(trade: &mut ProcessedTrade, execution: &SwapExecution) -> Result<(), Box<dyn Error>> {
+    let amount_bought = trade.amount_bought.unwrap_or_default();
+    let amount_sold: f64 = format_ether(execution.amount_out.unwrap_or(execution.quote.amount_out)).parse()?;
+    let gas_fee_paid: f64 = format_ether(execution.gas_fee).parse()?;
+    let multiplier = if amount_bought > 0.0 { amount_sold / amount_bought } else { 0.0 };
+    trade.closed_position(amount_sold, amount_sold - amount_bought, multiplier, gas_fee_paid)
+}
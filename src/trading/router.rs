@@ -0,0 +1,85 @@
+use std::{sync::Arc, error::Error, iter::once};
+
+use ethers::{
+    providers::{Provider, Http},
+    types::{Address, U256, U512},
+};
+
+use crate::{
+    bindings::erc20::Erc20,
+    dex::{BaseHop, DexAdapter},
+    models::config_models::NetworkMetadata,
+};
+
+// Where a token is traded: its own pool, and the base token in between when that pool has no wrapped native side
+#[derive(Debug, Clone, Copy)]
+pub struct TokenRoute {
+    pub pool: Address,
+    pub fee: Option<u32>, // Fee tier of the token's pool
+    pub via: Option<BaseHop>,
+}
+
+// Splits a new pair into the token to trade and the base it was launched against, None when there is nothing to trade
+pub fn pair_base(network_metadata: &NetworkMetadata, token_0: Address, token_1: Address) -> Option<(Address, Address)> {
+    match (is_base(network_metadata, token_0), is_base(network_metadata, token_1)) {
+        (true, false) => Some((token_1, token_0)),
+        (false, true) => Some((token_0, token_1)),
+        // Neither side can be bought with the native token, or both are bases and no token was launched
+        _ => None,
+    }
+}
+
+fn is_base(network_metadata: &NetworkMetadata, token: Address) -> bool {
+    token == network_metadata.wrapped_native_address || network_metadata.base_tokens.iter().any(|base| base.address == token)
+}
+
+// Hop between the wrapped native and the base token, None when the base is the wrapped native itself
+pub async fn base_hop(network_metadata: &NetworkMetadata, adapter: &dyn DexAdapter, base_token: Address) -> Result<Option<BaseHop>, Box<dyn Error>> {
+    if base_token == network_metadata.wrapped_native_address {
+        return Ok(None);
+    }
+    let provider = Arc::new(Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?);
+    let found = adapter.find_pool(provider, base_token, network_metadata.wrapped_native_address).await?
+        .ok_or_else(|| format!("[{}] No pool between base token {:?} and the wrapped native token", network_metadata.name, base_token))?;
+    Ok(Some(BaseHop { token: base_token, pool: found.pool, fee: found.fee }))
+}
+
+// Route for a token no pool event was seen for, e.g. a manual trade. The wrapped native is tried first, then the
+// base tokens in their configured order: depths against different bases are in different units and cannot be compared
+pub async fn find_route(network_metadata: &NetworkMetadata, adapter: &dyn DexAdapter, token: Address) -> Result<TokenRoute, Box<dyn Error>> {
+    let provider = Arc::new(Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?);
+    let bases = once((network_metadata.symbol.as_str(), network_metadata.wrapped_native_address))
+        .chain(network_metadata.base_tokens.iter().map(|base| (base.symbol.as_str(), base.address)));
+    for (symbol, base) in bases {
+        let Some(found) = adapter.find_pool(provider.clone(), token, base).await? else {
+            continue;
+        };
+        let via = base_hop(network_metadata, adapter, base).await?;
+        log::info!(
+            "[{}] Trading {:?} through pool {:?} (fee tier {:?}, base {}, base reserve {})",
+            network_metadata.name, token, found.pool, found.fee, symbol, found.depth,
+        );
+        return Ok(TokenRoute { pool: found.pool, fee: found.fee, via });
+    }
+    Err(format!("[{}] No pool found for {:?} against the wrapped native or any base token", network_metadata.name, token).into())
+}
+
+// Wrapped native balance of the pool, the side every trade of the bot goes through. Two-hop routes are as deep as
+// their shallower pool, the base balance of the token's pool is valued at the ratio of the hop pool
+pub async fn route_depth(network_metadata: &NetworkMetadata, route: &TokenRoute) -> Result<U256, Box<dyn Error>> {
+    let provider = Arc::new(Provider::<Http>::try_from(network_metadata.rpc_url.as_str())?);
+    let native = Erc20::new(network_metadata.wrapped_native_address, provider.clone());
+    let Some(via) = route.via else {
+        return Ok(native.balance_of(route.pool).call().await?);
+    };
+
+    let base = Erc20::new(via.token, provider);
+    let hop_native = native.balance_of(via.pool).call().await?;
+    let hop_base = base.balance_of(via.pool).call().await?;
+    let pool_base = base.balance_of(route.pool).call().await?;
+    if hop_base.is_zero() {
+        return Ok(U256::zero());
+    }
+    let pool_native = U256::try_from(pool_base.full_mul(hop_native) / U512::from(hop_base)).unwrap_or(U256::MAX);
+    Ok(pool_native.min(hop_native))
+}
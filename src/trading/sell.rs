@@ -1,152 +1,70 @@
 use std::{error::Error};
 
 use crate::{
-    models::config_models::{NetworkMetadata, ExchangeConfig},
-    bindings::{uniswap_v2_router02::UniswapV2Router02, uniswap_v3_smart_router::{UniswapV3SmartRouter, ExactInputSingleParams}},
-    network::{client::{create_client_arc}, transaction::{send_tx, send_tx_flashbots}}
+    models::{config_models::{NetworkMetadata, ExchangeConfig}, security_models::TransferTax},
+    dex::{adapter_for, DexAdapter, SwapDirection, SwapRequest},
+    network::gas::Urgency,
 };
 
 use ethers::{
-    prelude::*,
     types::{Address, U256},
-    utils::parse_ether, providers::Middleware,
 };
-use chrono::Utc;
 
-pub async fn sell_token(
-    exchange: ExchangeConfig,
-    network_metadata: NetworkMetadata,
-    target_token_address: Address,
-    amount_in_tokens: U256,
-    amount_out_min: U256,
-    is_v3: bool,
-    fee: Option<u32>,
-) -> Result<H256, Box<dyn Error>> {
-    if is_v3 {
-        sell_v3(
-            exchange,
-            network_metadata,
-            target_token_address,
-            amount_in_tokens,
-            amount_out_min,
-            fee,
-        )
-        .await
-    } else {
-        sell_v2(
-            exchange,
-            network_metadata,
-            target_token_address,
-            amount_in_tokens,
-            amount_out_min,
-        )
-        .await
-    }
-}
+use super::{executor::{execute_swap, quote_swap, SwapExecution}, router::TokenRoute, slippage::{apply_slippage, apply_transfer_tax, trade_slippage_bps}};
 
-async fn sell_v2(
+pub async fn sell_token(
     exchange: ExchangeConfig,
     network_metadata: NetworkMetadata,
     target_token_address: Address,
     amount_in_tokens: U256,
     amount_out_min: U256,
-) -> Result<H256, Box<dyn Error>> {
-    let client_arc = create_client_arc(network_metadata.rpc_url.as_str(), network_metadata.clone().chain_id).await?;
-    let router_contract_address: Address = exchange.router_contract_address.parse()?;
-    let uniswap_v2_router = UniswapV2Router02::new(router_contract_address, client_arc.clone());
+    route: TokenRoute,
+    transfer_tax: TransferTax,
+) -> Result<SwapExecution, Box<dyn Error>> {
+    let adapter = adapter_for(&exchange)?;
 
-    let deadline = Utc::now().timestamp() as u64 + 15 * 60;
-    let deadline_u256: U256 = U256::from(deadline);
-
-    let path: Vec<Address> = vec![target_token_address, network_metadata.wrapped_native_address];
-
-    let function_call = uniswap_v2_router.swap_exact_tokens_for_eth(
-        amount_in_tokens,
-        amount_out_min,
-        path,
-        network_metadata.wallet_address,
-        deadline_u256,
-    );
-
-    let estimated_gas = function_call.estimate_gas().await?;
-    let gas_price = client_arc.get_gas_price().await?;
-    let chain_id: U64 = network_metadata.chain_id.parse()?;
-
-    let tx_request = TransactionRequest {
-        chain_id: Some(chain_id),
-        from: Some(network_metadata.wallet_address.into()),
-        to: Some(NameOrAddress::Address(router_contract_address)),
-        gas: Some(estimated_gas),
-        gas_price: Some(gas_price),
-        value: None,  // No ETH sent along with token swap
-        data: Some(function_call.tx.data().unwrap().clone()),
-        nonce: None,
+    let mut request = SwapRequest {
+        direction: SwapDirection::TokensForNative,
+        token_in: target_token_address,
+        token_out: network_metadata.wrapped_native_address,
+        amount_in: amount_in_tokens,
+        amount_out_min: U256::zero(),
+        recipient: network_metadata.wallet_address,
+        fee: route.fee,
+        fee_on_transfer: transfer_tax.is_taxed(),
+        via: route.via,
     };
 
-    if network_metadata.chain_id == "1" {
-        send_tx_flashbots(
-            &network_metadata,
-            tx_request,
-        ).await
-    } else {
-        send_tx(
-            client_arc,
-            tx_request,
-        ).await
-    }
+    // Quote the expected output so the execution records it, simulated fills rely on it
+    let mut quote = quote_swap(adapter.as_ref(), &network_metadata, &request).await?;
+    quote.amount_out = apply_transfer_tax(quote.amount_out, transfer_tax.sell);
+    request.amount_out_min = amount_out_min;
+
+    execute_swap(adapter.as_ref(), &network_metadata, &request, quote, Urgency::Exit).await
 }
 
-async fn sell_v3(
-    exchange: ExchangeConfig,
-    network_metadata: NetworkMetadata,
+// Least a sale of `amount_in_tokens` may return: the quote, less what the token keeps and the allowed slippage
+pub async fn min_sell_output(
+    adapter: &dyn DexAdapter,
+    exchange: &ExchangeConfig,
+    network_metadata: &NetworkMetadata,
     target_token_address: Address,
     amount_in_tokens: U256,
-    amount_out_min: U256,
-    fee: Option<u32>,
-) -> Result<H256, Box<dyn Error>> {
-
-    let client_arc = create_client_arc(network_metadata.rpc_url.as_str(), network_metadata.chain_id.clone()).await?;
-    let router_contract_address: Address = exchange.router_contract_address.parse()?;
-    let uniswap_v3_router = UniswapV3SmartRouter::new(router_contract_address, client_arc.clone());
-
-    let default_fee = 3000; 
-    let fee = fee.unwrap_or(default_fee);
-
-    let params = ExactInputSingleParams {
+    route: &TokenRoute,
+    transfer_tax: TransferTax,
+) -> Result<U256, Box<dyn Error>> {
+    let request = SwapRequest {
+        direction: SwapDirection::TokensForNative,
         token_in: target_token_address,
         token_out: network_metadata.wrapped_native_address,
-        fee,
-        recipient: network_metadata.wallet_address,
         amount_in: amount_in_tokens,
-        amount_out_minimum: amount_out_min,
-        sqrt_price_limit_x96: U256::zero(),
-    };
-
-    let function_call = uniswap_v3_router.exact_input_single(params);
-    let estimated_gas = function_call.estimate_gas().await?;
-    let gas_price = client_arc.get_gas_price().await?;
-    let chain_id: U64 = network_metadata.chain_id.parse()?;
-
-    let tx_request = TransactionRequest {
-        chain_id: Some(chain_id),
-        from: Some(network_metadata.wallet_address.into()),
-        to: Some(NameOrAddress::Address(router_contract_address)),
-        gas: Some(estimated_gas),
-        gas_price: Some(gas_price),
-        value: None,  // No ETH sent along with token swap
-        data: Some(function_call.tx.data().unwrap().clone()),
-        nonce: None,
+        amount_out_min: U256::zero(),
+        recipient: network_metadata.wallet_address,
+        fee: route.fee,
+        fee_on_transfer: transfer_tax.is_taxed(),
+        via: route.via,
     };
-
-    if network_metadata.chain_id == "1" {
-        send_tx_flashbots(
-           &network_metadata,
-           tx_request
-        ).await
-    } else {
-        send_tx(
-           client_arc,
-           tx_request
-        ).await
-    }
-}
\ No newline at end of file
+    let amount_out = apply_transfer_tax(quote_swap(adapter, network_metadata, &request).await?.amount_out, transfer_tax.sell);
+    let slippage_bps = trade_slippage_bps(network_metadata, exchange, route, amount_out, transfer_tax.sell).await;
+    Ok(apply_slippage(amount_out, slippage_bps))
+}
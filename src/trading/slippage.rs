@@ -0,0 +1,147 @@
+use ethers::types::U256;
+
+use crate::models::config_models::{NetworkMetadata, ExchangeConfig, SlippagePolicy};
+
+use super::router::{route_depth, TokenRoute};
+
+pub const BPS_DENOMINATOR: u32 = 10_000;
+
+// The exchange's own slippage policy, or the network's when it has none
+pub fn slippage_policy<'a>(network_metadata: &'a NetworkMetadata, exchange: &'a ExchangeConfig) -> &'a SlippagePolicy {
+    exchange.slippage_policy.as_ref().unwrap_or(&network_metadata.slippage_policy)
+}
+
+// Tolerance for a trade moving `native_amount` through the route, widened for thin pools and taxed tokens
+pub async fn trade_slippage_bps(
+    network_metadata: &NetworkMetadata,
+    exchange: &ExchangeConfig,
+    route: &TokenRoute,
+    native_amount: U256,
+    tax: f64,
+) -> u32 {
+    let policy = slippage_policy(network_metadata, exchange);
+    let depth = match route_depth(network_metadata, route).await {
+        Ok(depth) => Some(depth),
+        Err(e) => {
+            log::warn!("[{} - {}] Failed to measure the depth of pool {:?}, using the base slippage: {}", network_metadata.name, exchange.name, route.pool, e);
+            None
+        },
+    };
+
+    let bps = slippage_bps(policy, native_amount, depth, tax);
+    log::info!(
+        "[{} - {}] Slippage of {} bps for {} wei through pool {:?} (native reserve {:?}, tax {})",
+        network_metadata.name, exchange.name, bps, native_amount, route.pool, depth, tax,
+    );
+    bps
+}
+
+// Base tolerance plus the dynamic terms, capped by the policy. An unknown depth adds nothing, an empty pool hits the cap
+pub fn slippage_bps(policy: &SlippagePolicy, native_amount: U256, pool_depth: Option<U256>, tax: f64) -> u32 {
+    let depth_bps = match pool_depth {
+        Some(depth) if depth.is_zero() => BPS_DENOMINATOR,
+        Some(depth) => share_bps(native_amount, depth),
+        None => 0,
+    };
+    let dynamic_bps = depth_bps as f64 * policy.depth_multiplier + tax_bps(tax) as f64 * policy.tax_multiplier;
+    (policy.base_bps as f64 + dynamic_bps).min(policy.max_bps as f64) as u32
+}
+
+// Lowest acceptable output for a quoted amount
+pub fn apply_slippage(amount: U256, slippage_bps: u32) -> U256 {
+    reduce_by_bps(amount, slippage_bps)
+}
+
+// Amount left after the token took its cut
+pub fn apply_transfer_tax(amount: U256, tax: f64) -> U256 {
+    reduce_by_bps(amount, tax_bps(tax))
+}
+
+// Taxes are rounded up so the net amount is never overestimated
+fn tax_bps(tax: f64) -> u32 {
+    (tax.clamp(0.0, 1.0) * BPS_DENOMINATOR as f64).ceil() as u32
+}
+
+// Share of the depth the amount represents, at most the whole of it
+fn share_bps(amount: U256, depth: U256) -> u32 {
+    if amount >= depth {
+        return BPS_DENOMINATOR;
+    }
+    // amount * 10000 only overflows for depths close to U256::MAX, where dividing the depth first loses nothing that matters
+    let denominator = U256::from(BPS_DENOMINATOR);
+    let bps = match amount.checked_mul(denominator) {
+        Some(scaled) => scaled / depth,
+        None => amount / (depth / denominator),
+    };
+    bps.min(denominator).as_u32()
+}
+
+// amount * (10000 - bps) / 10000 rounded down, split so it cannot overflow
+fn reduce_by_bps(amount: U256, bps: u32) -> U256 {
+    let kept = U256::from(BPS_DENOMINATOR - bps.min(BPS_DENOMINATOR));
+    let denominator = U256::from(BPS_DENOMINATOR);
+    amount / denominator * kept + amount % denominator * kept / denominator
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(base_bps: u32, max_bps: u32, depth_multiplier: f64, tax_multiplier: f64) -> SlippagePolicy {
+        SlippagePolicy { base_bps, max_bps, depth_multiplier, tax_multiplier }
+    }
+
+    #[test]
+    fn slippage_is_a_share_of_the_amount() {
+        let one_ether = U256::exp10(18);
+        assert_eq!(apply_slippage(one_ether, 2_000), U256::exp10(17) * 8);
+        assert_eq!(apply_slippage(one_ether, 1), one_ether - U256::exp10(14));
+    }
+
+    #[test]
+    fn slippage_at_the_bounds_of_the_amount() {
+        assert_eq!(apply_slippage(U256::zero(), 2_000), U256::zero());
+        assert_eq!(apply_slippage(U256::one(), 1), U256::zero()); // Rounded down
+        assert_eq!(apply_slippage(U256::from(9_999), 1), U256::from(9_998));
+        assert_eq!(apply_slippage(U256::MAX, 0), U256::MAX);
+        assert_eq!(apply_slippage(U256::MAX, BPS_DENOMINATOR), U256::zero());
+        assert_eq!(apply_slippage(U256::MAX, 5_000), U256::MAX / 2);
+    }
+
+    #[test]
+    fn slippage_above_u128_does_not_panic() {
+        let amount = U256::from(u128::MAX) + 1;
+        // Exactly amount * 99 / 100 rounded down, which is the amount less a hundredth rounded up
+        assert_eq!(apply_slippage(amount, 100), amount - (amount + 99) / 100);
+    }
+
+    #[test]
+    fn slippage_above_the_whole_amount_is_clamped() {
+        assert_eq!(apply_slippage(U256::from(1_000), 20_000), U256::zero());
+    }
+
+    #[test]
+    fn transfer_tax_is_rounded_up() {
+        assert_eq!(apply_transfer_tax(U256::from(10_000), 0.0), U256::from(10_000));
+        assert_eq!(apply_transfer_tax(U256::from(10_000), 0.00001), U256::from(9_999));
+        assert_eq!(apply_transfer_tax(U256::from(10_000), 0.05), U256::from(9_500));
+        assert_eq!(apply_transfer_tax(U256::from(10_000), 1.5), U256::zero());
+    }
+
+    #[test]
+    fn dynamic_slippage_terms() {
+        let depth = U256::exp10(20);
+        // Unknown depth and no tax leave the base
+        assert_eq!(slippage_bps(&policy(100, 5_000, 1.0, 1.0), depth, None, 0.0), 100);
+        // A trade of 1% of the pool adds 100 bps, a 5% tax adds 500 bps
+        assert_eq!(slippage_bps(&policy(100, 5_000, 1.0, 0.0), depth / 100, Some(depth), 0.0), 200);
+        assert_eq!(slippage_bps(&policy(100, 5_000, 0.0, 1.0), depth, Some(depth), 0.05), 600);
+    }
+
+    #[test]
+    fn dynamic_slippage_is_capped() {
+        assert_eq!(slippage_bps(&policy(100, 3_000, 1.0, 0.0), U256::one(), Some(U256::zero()), 0.0), 3_000);
+        assert_eq!(slippage_bps(&policy(100, 3_000, 1.0, 0.0), U256::MAX, Some(U256::one()), 0.0), 3_000);
+        assert_eq!(slippage_bps(&policy(100, 3_000, 0.0, 10.0), U256::zero(), None, 1.0), 3_000);
+    }
+}
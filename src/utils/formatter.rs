@@ -1,3 +1,5 @@
+use ethers::{types::U256, utils::{parse_units, ConversionError}};
+
 pub fn parse_f64_field(field: &Option<String>) -> f64 {
     match field {
         Some(value) => value.parse::<f64>().unwrap_or_default(),  // Parses the string into f64, returns 0.0 if it fails
@@ -10,4 +12,14 @@ pub fn parse_i32_field(field: &Option<String>) -> i32 {
         Some(value) => value.parse::<i32>().unwrap_or_default(),
         None => 0,
     }
-}
\ No newline at end of file
+}
+
+// Lossy conversion for amounts that can exceed u128
+pub fn u256_to_f64(value: U256) -> f64 {
+    value.to_string().parse::<f64>().unwrap_or_default()
+}
+
+// Native amount in wei, rounded to 18 decimals: the plain f64 string of a tiny amount has more than parse_ether accepts
+pub fn native_to_wei(amount: f64) -> Result<U256, ConversionError> {
+    Ok(parse_units(format!("{:.18}", amount), "ether")?.into())
+}
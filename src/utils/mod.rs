@@ -1,2 +1 @@
 pub mod formatter;
-pub mod csv_manager;
\ No newline at end of file